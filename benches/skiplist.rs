@@ -0,0 +1,191 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use limonitedb::memtable::linkedlist_skiplist::LinkedListSkipList;
+use limonitedb::memtable::skiplist::SkipList;
+
+const KEYS: i64 = 1_000_000;
+
+fn build<const MAX_HEIGHT: usize>(keys: &[i64]) -> LinkedListSkipList<'static, i64, MAX_HEIGHT> {
+    let mut list = LinkedListSkipList::new();
+    for key in keys {
+        list.insert(*key);
+    }
+    list
+}
+
+fn shuffled_keys() -> Vec<i64> {
+    let mut keys: Vec<i64> = (0..KEYS).collect();
+    fastrand::shuffle(&mut keys);
+    keys
+}
+
+fn bench_height<const MAX_HEIGHT: usize>(c: &mut Criterion) {
+    let sequential: Vec<i64> = (0..KEYS).collect();
+    let random = shuffled_keys();
+
+    let mut group = c.benchmark_group("linkedlist_skiplist");
+    group.sample_size(10);
+
+    group.bench_function(BenchmarkId::new("sequential_insert", MAX_HEIGHT), |b| {
+        b.iter_batched(|| (), |_| build::<MAX_HEIGHT>(&sequential), BatchSize::PerIteration)
+    });
+
+    group.bench_function(BenchmarkId::new("random_insert", MAX_HEIGHT), |b| {
+        b.iter_batched(|| (), |_| build::<MAX_HEIGHT>(&random), BatchSize::PerIteration)
+    });
+
+    let list = build::<MAX_HEIGHT>(&random);
+    group.bench_function(BenchmarkId::new("point_lookup", MAX_HEIGHT), |b| {
+        b.iter(|| {
+            for key in random.iter().take(10_000) {
+                black_box(list.contains(key));
+            }
+        })
+    });
+    drop(list);
+
+    group.bench_function(BenchmarkId::new("full_scan", MAX_HEIGHT), |b| {
+        b.iter_batched(
+            || build::<MAX_HEIGHT>(&random),
+            |list| {
+                let mut iter = list.into_iter();
+                for key in iter.by_ref() {
+                    black_box(key);
+                }
+                iter // Returned so the list is dropped outside of the measurement.
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+// Writes a buffer larger than the last level cache so the list's nodes are evicted from it.
+fn evict_cache() {
+    let mut buffer = vec![0_u8; 64 * 1024 * 1024];
+    for byte in buffer.iter_mut().step_by(64) {
+        *byte = 1;
+    }
+    black_box(buffer);
+}
+
+// The first lookups after the list has fallen out of the cache, with and without a prefetch.
+fn bench_prefetch(c: &mut Criterion) {
+    let random = shuffled_keys();
+    let list = build::<32>(&random);
+    let mut group = c.benchmark_group("linkedlist_skiplist");
+    group.sample_size(10);
+    for prefetch in [false, true] {
+        group.bench_function(BenchmarkId::new("first_lookups", if prefetch { "prefetched" } else { "cold" }), |b| {
+            b.iter_batched(
+                || {
+                    evict_cache();
+                    if prefetch {
+                        list.prefetch();
+                    }
+                },
+                |_| {
+                    for key in random.iter().take(1_000) {
+                        black_box(list.contains(key));
+                    }
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+// Lookups in a list only a few levels tall but allowed to grow far taller, where a search only
+// needs the levels in use.
+fn bench_small_list(c: &mut Criterion) {
+    let keys: Vec<i64> = (0..64).collect();
+    let list = build::<64>(&keys);
+    let mut group = c.benchmark_group("linkedlist_skiplist");
+    group.bench_function(BenchmarkId::new("small_list_lookup", 64), |b| {
+        b.iter(|| {
+            for key in &keys {
+                black_box(list.contains(key));
+            }
+        })
+    });
+    let mut list = build::<64>(&keys);
+    group.bench_function(BenchmarkId::new("small_list_remove_insert", 64), |b| {
+        b.iter(|| {
+            for key in &keys {
+                black_box(list.remove(key));
+                list.insert(*key);
+            }
+        })
+    });
+    group.finish();
+}
+
+// Ascending inserts, linked after the cached tails of the previous insert or searched for from
+// the head.
+fn bench_append_hint(c: &mut Criterion) {
+    let sequential: Vec<i64> = (0..KEYS).collect();
+    let mut group = c.benchmark_group("linkedlist_skiplist");
+    group.sample_size(10);
+    for hinted in [true, false] {
+        group.bench_function(BenchmarkId::new("ascending_insert", if hinted { "hinted" } else { "searched" }), |b| {
+            b.iter_batched(
+                || (),
+                |_| {
+                    let mut list: LinkedListSkipList<i64, 32> = LinkedListSkipList::new().with_append_hint(hinted);
+                    for key in &sequential {
+                        list.insert(*key);
+                    }
+                    list
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+// Lookups in a list searched through its links, and frozen with its upper levels packed into an
+// array searched first.
+fn bench_frozen(c: &mut Criterion) {
+    let random = shuffled_keys();
+    let list = build::<32>(&random);
+    let height = list.get_max_height();
+    let mut group = c.benchmark_group("linkedlist_skiplist");
+    group.sample_size(10);
+    group.bench_function(BenchmarkId::new("frozen_lookup", "linked"), |b| {
+        b.iter(|| {
+            for key in random.iter().take(10_000) {
+                black_box(list.contains(key));
+            }
+        })
+    });
+    let mut list = Some(list);
+    // Packing down to the level with about 16k keys, then about 1k keys.
+    for index_levels in [height - 6, height - 10] {
+        let frozen = list.take().unwrap().freeze(index_levels);
+        group.bench_function(BenchmarkId::new("frozen_lookup", format!("{}_keys_packed", frozen.index_len())), |b| {
+            b.iter(|| {
+                for key in random.iter().take(10_000) {
+                    black_box(frozen.contains(key));
+                }
+            })
+        });
+        list = Some(frozen.thaw());
+    }
+    group.finish();
+}
+
+fn skiplist_benches(c: &mut Criterion) {
+    bench_height::<16>(c);
+    bench_height::<32>(c);
+    bench_height::<64>(c);
+    bench_prefetch(c);
+    bench_small_list(c);
+    bench_append_hint(c);
+    bench_frozen(c);
+}
+
+criterion_group!(benches, skiplist_benches);
+criterion_main!(benches);
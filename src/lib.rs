@@ -1,11 +1,22 @@
-mod memtable {
-    mod skiplist;
-    mod linkedlist_skiplist;
+pub mod memtable {
+    pub mod allocator;
+    pub mod encoding;
+    pub mod flush;
+    pub mod skiplist;
+    pub mod linkedlist_skiplist;
+    pub mod map;
+    pub mod merge;
+    pub mod readers;
+    #[cfg(feature = "recorder")]
+    pub mod recorder;
+    pub mod store;
+    pub mod striped;
+    #[allow(clippy::module_inception)]
+    pub mod memtable;
 }
 mod wal {
+    #[allow(clippy::module_inception)]
     mod wal;
     mod wal_manager;
 }
 mod limonitedb;
-
-
@@ -0,0 +1,144 @@
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::cell::{Cell, RefCell};
+use std::cmp::max;
+use std::ptr::NonNull;
+
+/// Allocates the memory backing skip list nodes, letting callers place nodes in a custom pool or
+/// allocator rather than the global heap.
+///
+/// # Safety
+/// Memory returned by `allocate` must be valid for reads and writes of `layout` until it is passed
+/// back to `deallocate`. Lists which exchange nodes, through `split_off` and `append`, free them
+/// through a different value of the allocator type than allocated them, so any value must be able
+/// to free memory allocated by another. Memory which only lives as long as the allocator that
+/// returned it must be handed over in `absorb`.
+pub unsafe trait NodeAllocator {
+    /// Allocates memory fitting `layout`. Must not return on failure, see `handle_alloc_error`.
+    fn allocate(&self, layout: Layout) -> NonNull<u8>;
+
+    /// Releases memory previously returned by `allocate`.
+    ///
+    /// # Safety
+    /// `ptr` must have been returned by `allocate` on this allocator with the same `layout`.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+
+    /// Called when the nodes of a list allocated from `other` are moved into a list allocated from
+    /// this one, before `other` is dropped.
+    fn absorb(&mut self, _other: &mut Self)
+    where
+        Self: Sized,
+    {
+    }
+
+    /// Returns the bytes held by an allocator which pools memory, including any not yet handed
+    /// out or already freed, or None if each allocation is held only until it is deallocated.
+    fn reserved_bytes(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Allocates nodes from the global allocator.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Global;
+
+unsafe impl NodeAllocator for Global {
+    fn allocate(&self, layout: Layout) -> NonNull<u8> {
+        debug_assert!(layout.size() > 0);
+        match NonNull::new(unsafe { alloc(layout) }) {
+            Some(ptr) => ptr,
+            None => handle_alloc_error(layout),
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        dealloc(ptr.as_ptr(), layout)
+    }
+}
+
+/// Allocates nodes by bumping a pointer through large chunks of memory, releasing everything at
+/// once when the arena is dropped rather than node by node.
+pub struct Arena {
+    chunks: RefCell<Vec<(NonNull<u8>, Layout)>>,
+    // Next free byte in the newest chunk and the number of bytes after it.
+    cursor: Cell<*mut u8>,
+    remaining: Cell<usize>,
+}
+
+const ARENA_CHUNK_SIZE: usize = 64 * 1024;
+const ARENA_CHUNK_ALIGN: usize = 16;
+
+impl Arena {
+    pub fn new() -> Self {
+        Self {
+            chunks: RefCell::new(Vec::new()),
+            cursor: Cell::new(std::ptr::null_mut()),
+            remaining: Cell::new(0),
+        }
+    }
+
+    /// Creates an arena with a first chunk of at least `bytes`.
+    pub fn with_capacity(bytes: usize) -> Self {
+        let arena = Self::new();
+        if bytes > 0 {
+            arena.grow(bytes, ARENA_CHUNK_ALIGN);
+        }
+        arena
+    }
+
+    /// Returns the number of chunks the arena has allocated.
+    pub fn chunks(&self) -> usize {
+        self.chunks.borrow().len()
+    }
+
+    fn grow(&self, size: usize, align: usize) {
+        let layout = Layout::from_size_align(max(size, ARENA_CHUNK_SIZE), max(align, ARENA_CHUNK_ALIGN))
+            .expect("arena chunk layout overflows");
+        let chunk = Global.allocate(layout);
+        self.chunks.borrow_mut().push((chunk, layout));
+        self.cursor.set(chunk.as_ptr());
+        self.remaining.set(layout.size());
+    }
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl NodeAllocator for Arena {
+    fn allocate(&self, layout: Layout) -> NonNull<u8> {
+        let padding = self.cursor.get().align_offset(layout.align());
+        if self.cursor.get().is_null() || padding + layout.size() > self.remaining.get() {
+            self.grow(layout.size(), layout.align());
+            return self.allocate(layout);
+        }
+        let ptr = unsafe { self.cursor.get().add(padding) };
+        self.cursor.set(unsafe { ptr.add(layout.size()) });
+        self.remaining.set(self.remaining.get() - padding - layout.size());
+        unsafe { NonNull::new_unchecked(ptr) }
+    }
+
+    // Memory is only released when the arena is dropped.
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+
+    fn absorb(&mut self, other: &mut Self) {
+        // Keep allocating from this arena's newest chunk, `other`'s chunks only need to outlive it.
+        let mut chunks = other.chunks.take();
+        chunks.append(self.chunks.get_mut());
+        *self.chunks.get_mut() = chunks;
+        other.remaining.set(0);
+    }
+
+    fn reserved_bytes(&self) -> Option<usize> {
+        Some(self.chunks.borrow().iter().map(|(_, layout)| layout.size()).sum())
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        for (chunk, layout) in self.chunks.get_mut().drain(..) {
+            unsafe { Global.deallocate(chunk, layout) };
+        }
+    }
+}
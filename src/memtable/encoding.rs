@@ -0,0 +1,97 @@
+/// Encodes keys to bytes which sort, compared lexicographically, in the same order as the keys
+/// themselves, so flushed keys can be compared and searched without decoding them.
+pub trait OrderedEncode: Ord + Sized {
+    /// Appends the encoding of `self` to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decodes a key from the front of `input`, advancing `input` past it. Returns None if
+    /// `input` does not start with a whole encoded key.
+    fn decode(input: &mut &[u8]) -> Option<Self>;
+}
+
+// Unsigned integers are written big endian, most significant byte first.
+macro_rules! ordered_encode_unsigned {
+    ($($int:ty),*) => {$(
+        impl OrderedEncode for $int {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_be_bytes());
+            }
+
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                let (bytes, rest) = input.split_first_chunk()?;
+                *input = rest;
+                Some(<$int>::from_be_bytes(*bytes))
+            }
+        }
+    )*};
+}
+
+// Signed integers are written big endian with the sign bit flipped, so negative numbers sort
+// before positive ones.
+macro_rules! ordered_encode_signed {
+    ($($int:ty),*) => {$(
+        impl OrderedEncode for $int {
+            fn encode(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&(*self ^ <$int>::MIN).to_be_bytes());
+            }
+
+            fn decode(input: &mut &[u8]) -> Option<Self> {
+                let (bytes, rest) = input.split_first_chunk()?;
+                *input = rest;
+                Some(<$int>::from_be_bytes(*bytes) ^ <$int>::MIN)
+            }
+        }
+    )*};
+}
+
+ordered_encode_unsigned!(u8, u16, u32, u64, u128);
+ordered_encode_signed!(i8, i16, i32, i64, i128);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memtable::linkedlist_skiplist::seed_test_rng;
+
+    fn encoded<Key: OrderedEncode>(key: &Key) -> Vec<u8> {
+        let mut out = Vec::new();
+        key.encode(&mut out);
+        out
+    }
+
+    fn assert_order_preserved<Key: OrderedEncode + Copy + std::fmt::Debug>(keys: &[Key]) {
+        for a in keys {
+            let mut input = &encoded(a)[..];
+            assert_eq!(Key::decode(&mut input), Some(*a));
+            assert!(input.is_empty());
+            for b in keys {
+                assert_eq!(a.cmp(b), encoded(a).cmp(&encoded(b)), "{:?} against {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_integer_order_preserved() {
+        seed_test_rng();
+        let edges = [i64::MIN, i64::MIN + 1, -256, -1, 0, 1, 255, 256, i64::MAX];
+        assert_order_preserved(&edges);
+        assert_order_preserved(&(0..200).map(|_| fastrand::i64(..)).collect::<Vec<_>>());
+        assert_order_preserved(&(0..200).map(|_| fastrand::i32(-1000..1000)).collect::<Vec<_>>());
+        assert_order_preserved(&(0..200).map(|_| fastrand::i8(..)).collect::<Vec<_>>());
+        assert_order_preserved(&(0..200).map(|_| fastrand::i128(..)).collect::<Vec<_>>());
+        assert_order_preserved(&(0..200).map(|_| fastrand::u64(..)).collect::<Vec<_>>());
+        assert_order_preserved(&(0..200).map(|_| fastrand::u16(..)).collect::<Vec<_>>());
+        assert_order_preserved(&[0_u32, 1, 255, 256, u32::MAX]);
+    }
+
+    #[test]
+    fn test_decode_sequence() {
+        let mut out = Vec::new();
+        (-5_i32).encode(&mut out);
+        7_u16.encode(&mut out);
+        let mut input = &out[..];
+        assert_eq!(i32::decode(&mut input), Some(-5));
+        assert_eq!(u16::decode(&mut input), Some(7));
+        assert_eq!(u16::decode(&mut input), None);
+        assert_eq!(u64::decode(&mut &out[..4]), None);
+    }
+}
@@ -0,0 +1,802 @@
+use std::fs::OpenOptions;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use memmap2::MmapMut;
+
+use crate::memtable::allocator::NodeAllocator;
+use crate::memtable::encoding::OrderedEncode;
+use crate::memtable::linkedlist_skiplist::LinkedListSkipList;
+use crate::memtable::merge::MergingIterator;
+
+/// How keys are laid out in a flushed byte stream. The stream starts with `FLUSH_MAGIC`, the format
+/// version and a single byte naming the mode, followed by one record per key in ascending order.
+/// The first varint of each record is stored plus one, so a zero ends the records. After them
+/// comes a sparse index of every `SPARSE_INDEX_INTERVAL`th key as `varint(count)` then
+/// `varint(len) key varint(offset)` per entry, and last the offset of the index as a little endian
+/// u64. Each indexed key starts a block which can be decoded on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushMode {
+    /// Each key is written in full as `varint(len) key`.
+    Plain,
+    /// Each key is written relative to the key before it as `varint(shared) varint(len) suffix`,
+    /// where `shared` is the length of the prefix the two keys have in common. Sorted keys often
+    /// differ only in their last few bytes so this is usually much smaller than `Plain`.
+    Delta,
+}
+
+/// Bytes every flushed stream starts with.
+pub const FLUSH_MAGIC: [u8; 4] = *b"LMFL";
+
+/// Version of the format written by flushes. Bumped whenever the layout of a stream changes, so
+/// readers can reject streams written in a format they do not know. Version 1 streams, which
+/// have no end marker or index, are still read by `FlushReader`.
+pub const FLUSH_VERSION: u8 = 2;
+
+/// Keys between entries of a flushed stream's sparse index. A lookup through `FlushedTable`
+/// decodes at most this many records.
+pub const SPARSE_INDEX_INTERVAL: usize = 16;
+
+// Bytes of the footer holding the offset of the sparse index.
+const FOOTER_LEN: usize = 8;
+
+impl FlushMode {
+    fn tag(self) -> u8 {
+        match self {
+            FlushMode::Plain => 0,
+            FlushMode::Delta => 1,
+        }
+    }
+
+    fn from_tag(tag: u8) -> io::Result<Self> {
+        match tag {
+            0 => Ok(FlushMode::Plain),
+            1 => Ok(FlushMode::Delta),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown flush mode {}", tag))),
+        }
+    }
+}
+
+impl<'a, Key: Ord + Default + AsRef<[u8]>, const MAX_HEIGHT: usize, A: NodeAllocator> LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {
+    /// Writes every key in ascending order to `w` using `mode`. Read the keys back with
+    /// `FlushReader`. Only the keys are written, nothing of the list's shape such as node heights,
+    /// so lists holding the same keys flush to the same bytes whatever order they were built in.
+    pub fn flush<W: Write>(&self, w: &mut W, mode: FlushMode) -> io::Result<()> {
+        write_keys(self.iter(), w, mode)
+    }
+
+    /// Groups the keys, in ascending order, into blocks of consecutive keys whose `FlushMode::Plain`
+    /// records total at most `target_block_bytes`, for writing a block at a time. A key whose
+    /// record alone is larger than the target is given a block of its own.
+    pub fn block_iter(&self, target_block_bytes: usize) -> impl Iterator<Item = Vec<&Key>> + '_ {
+        let mut keys = self.iter().peekable();
+        std::iter::from_fn(move || {
+            let mut block = Vec::new();
+            let mut block_bytes = 0;
+            while let Some(key) = keys.next_if(|key| block.is_empty() || block_bytes + plain_record_len(key.as_ref()) <= target_block_bytes) {
+                block_bytes += plain_record_len(key.as_ref());
+                block.push(key);
+            }
+            (!block.is_empty()).then_some(block)
+        })
+    }
+
+    /// Writes the keys of every list in `tables` to `w` as one sorted stream in the same format
+    /// as `flush`. `tables` are listed newest first, a key in more than one list is written once
+    /// as it is in the newest, so a delete recorded in a newer list wins over older writes.
+    pub fn flush_merged<W: Write>(tables: &[&Self], w: &mut W, mode: FlushMode) -> io::Result<()> {
+        write_keys(MergingIterator::new(tables.iter().map(|table| table.iter())), w, mode)
+    }
+
+    /// Writes the same stream as `flush` to the file at `path`, replacing any file already there.
+    /// The file is sized up front and the keys are written straight into a memory map of it rather
+    /// than through a buffered writer. An empty list produces a file holding only the header, the
+    /// end marker and an empty index with its footer.
+    pub fn flush_mmap(&self, path: &Path, mode: FlushMode) -> io::Result<()> {
+        let mut counter = ByteCounter(0);
+        self.flush(&mut counter, mode)?;
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        file.set_len(counter.0 as u64)?;
+        // Safety: the file was just truncated by us and is not expected to be touched by anything
+        // else while it is being written.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        self.flush(&mut &mut map[..], mode)?;
+        map.flush()
+    }
+}
+
+impl<'a, Key: Ord + Default + OrderedEncode, const MAX_HEIGHT: usize, A: NodeAllocator> LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {
+    /// As `flush` for keys which are not bytes themselves, writing each key's `OrderedEncode`
+    /// encoding. The flushed keys sort in the same order as the keys in the list.
+    pub fn flush_encoded<W: Write>(&self, w: &mut W, mode: FlushMode) -> io::Result<()> {
+        let encoded = self.iter().map(|key| {
+            let mut out = Vec::new();
+            key.encode(&mut out);
+            out
+        });
+        write_keys(encoded, w, mode)
+    }
+}
+
+fn write_keys<Key: AsRef<[u8]>, W: Write>(keys: impl Iterator<Item = Key>, w: &mut W, mode: FlushMode) -> io::Result<()> {
+    let mut w = OffsetWriter { inner: w, offset: 0 };
+    w.write_all(&FLUSH_MAGIC)?;
+    w.write_all(&[FLUSH_VERSION, mode.tag()])?;
+    let mut previous: Option<Key> = None;
+    let mut index: Vec<(Vec<u8>, usize)> = Vec::new();
+    for (i, key) in keys.enumerate() {
+        let bytes = key.as_ref();
+        // Indexed keys are written whole so their block can be decoded without the key before.
+        let indexed = i % SPARSE_INDEX_INTERVAL == 0;
+        if indexed {
+            index.push((bytes.to_vec(), w.offset));
+        }
+        match mode {
+            FlushMode::Plain => {
+                write_varint(&mut w, bytes.len() as u64 + 1)?;
+                w.write_all(bytes)?;
+            }
+            FlushMode::Delta => {
+                let shared = match &previous {
+                    Some(previous) if !indexed => shared_prefix_len(previous.as_ref(), bytes),
+                    _ => 0,
+                };
+                write_varint(&mut w, shared as u64 + 1)?;
+                write_varint(&mut w, (bytes.len() - shared) as u64)?;
+                w.write_all(&bytes[shared..])?;
+            }
+        }
+        previous = Some(key);
+    }
+    write_varint(&mut w, 0)?;
+    let index_offset = w.offset;
+    write_varint(&mut w, index.len() as u64)?;
+    for (key, offset) in &index {
+        write_varint(&mut w, key.len() as u64)?;
+        w.write_all(key)?;
+        write_varint(&mut w, *offset as u64)?;
+    }
+    w.write_all(&(index_offset as u64).to_le_bytes())
+}
+
+// Passes writes through, counting the bytes so far to record the offsets of indexed keys.
+struct OffsetWriter<'w, W: Write> {
+    inner: &'w mut W,
+    offset: usize,
+}
+
+impl<W: Write> Write for OffsetWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.offset += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Counts the bytes written to it, used to size a flush before writing it.
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads the keys written by `LinkedListSkipList::flush` back in order, reconstructing full keys
+/// from delta encoded records. Streams which do not start with `FLUSH_MAGIC` or were written in
+/// an unknown version of the format are rejected by `new`.
+pub struct FlushReader<R: Read> {
+    reader: R,
+    mode: FlushMode,
+    previous: Vec<u8>,
+    // Whether the records end with a zero, which they do from version 2, and if it was read.
+    end_marked: bool,
+    ended: bool,
+    // Set when reading a single block of a stream, which ends without the zero.
+    block: bool,
+}
+
+impl<R: Read> FlushReader<R> {
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; FLUSH_MAGIC.len() + 1];
+        reader.read_exact(&mut header)?;
+        if header[..FLUSH_MAGIC.len()] != FLUSH_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a flushed stream, magic does not match"));
+        }
+        let version = header[FLUSH_MAGIC.len()];
+        let mode = match version {
+            1 | 2 => {
+                let mut tag = [0; 1];
+                reader.read_exact(&mut tag)?;
+                FlushMode::from_tag(tag[0])?
+            }
+            version => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unsupported flush format version {}, newest supported is {}", version, FLUSH_VERSION),
+                ))
+            }
+        };
+        Ok(Self::records(reader, mode, version >= 2, false))
+    }
+
+    // Reads the records from `reader`, positioned after the header or at the start of a block.
+    fn records(reader: R, mode: FlushMode, end_marked: bool, block: bool) -> Self {
+        Self {
+            reader,
+            mode,
+            previous: Vec::new(),
+            end_marked,
+            ended: false,
+            block,
+        }
+    }
+
+    pub fn mode(&self) -> FlushMode {
+        self.mode
+    }
+
+    fn read_key(&mut self, first: u64) -> io::Result<Vec<u8>> {
+        let key = match self.mode {
+            FlushMode::Plain => read_bytes(&mut self.reader, first)?,
+            FlushMode::Delta => {
+                let shared = first as usize;
+                if shared > self.previous.len() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "shared prefix longer than previous key"));
+                }
+                let len = read_varint(&mut self.reader)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+                let mut key = self.previous[..shared].to_vec();
+                key.extend(read_bytes(&mut self.reader, len)?);
+                self.previous.clone_from(&key);
+                key
+            }
+        };
+        Ok(key)
+    }
+}
+
+impl<R: Read> Iterator for FlushReader<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.ended {
+            return None;
+        }
+        match read_varint(&mut self.reader) {
+            Ok(Some(0)) if self.end_marked => {
+                self.ended = true;
+                None
+            }
+            Ok(Some(first)) if self.end_marked => Some(self.read_key(first - 1)),
+            Ok(Some(first)) => Some(self.read_key(first)),
+            Ok(None) if self.end_marked && !self.block => Some(Err(io::ErrorKind::UnexpectedEof.into())),
+            Ok(None) => None,
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
+
+/// A flushed stream held in memory, such as a memory mapped file, searched through its sparse
+/// index without decoding every key.
+pub struct FlushedTable<'b> {
+    bytes: &'b [u8],
+    mode: FlushMode,
+    // Every indexed key and the offset of its record, in ascending order.
+    index: Vec<(&'b [u8], usize)>,
+    // Offset of the marker ending the records.
+    records_end: usize,
+}
+
+impl<'b> FlushedTable<'b> {
+    /// Reads the header, footer and sparse index of a stream written by a flush. Streams of
+    /// version 1, which have no index, are rejected.
+    pub fn open(bytes: &'b [u8]) -> io::Result<Self> {
+        let header = FlushReader::new(bytes)?;
+        if !header.end_marked {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "flush format version 1 has no index"));
+        }
+        let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, format!("corrupt flushed table, {}", reason));
+        let footer = bytes.len().checked_sub(FOOTER_LEN).ok_or_else(|| invalid("no footer"))?;
+        let index_offset = u64::from_le_bytes(bytes[footer..].try_into().unwrap()) as usize;
+        if index_offset > footer || index_offset <= FLUSH_MAGIC.len() + 2 {
+            return Err(invalid("index offset out of bounds"));
+        }
+        let records_start = FLUSH_MAGIC.len() + 2;
+        let records_end = index_offset - 1;
+        let mut input = &bytes[index_offset..footer];
+        let count = read_varint(&mut input)?.ok_or(io::ErrorKind::UnexpectedEof)?;
+        let mut index: Vec<(&[u8], usize)> = Vec::new();
+        for _ in 0..count {
+            let len = read_varint(&mut input)?.ok_or(io::ErrorKind::UnexpectedEof)? as usize;
+            if len > input.len() {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            let (key, rest) = input.split_at(len);
+            input = rest;
+            let offset = read_varint(&mut input)?.ok_or(io::ErrorKind::UnexpectedEof)? as usize;
+            if offset < records_start || offset >= records_end {
+                return Err(invalid("indexed record out of bounds"));
+            }
+            // Blocks are sliced between consecutive offsets and searched by their first keys.
+            if let Some(&(previous_key, previous_offset)) = index.last() {
+                if offset <= previous_offset {
+                    return Err(invalid("indexed records out of order"));
+                }
+                if key <= previous_key {
+                    return Err(invalid("indexed keys out of order"));
+                }
+            }
+            index.push((key, offset));
+        }
+        Ok(Self {
+            bytes,
+            mode: header.mode,
+            index,
+            records_end,
+        })
+    }
+
+    pub fn mode(&self) -> FlushMode {
+        self.mode
+    }
+
+    /// Returns the stored key equal to `key`, found by a binary search of the sparse index then a
+    /// scan of the one block which could hold it.
+    pub fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        let block = self.index.partition_point(|(indexed, _)| *indexed <= key);
+        let Some(block) = block.checked_sub(1) else {
+            return Ok(None); // Smaller than every key.
+        };
+        let start = self.index[block].1;
+        let end = self.index.get(block + 1).map_or(self.records_end, |(_, offset)| *offset);
+        for stored in FlushReader::records(&self.bytes[start..end], self.mode, true, true) {
+            let stored = stored?;
+            match stored.as_slice().cmp(key) {
+                std::cmp::Ordering::Less => continue,
+                std::cmp::Ordering::Equal => return Ok(Some(stored)),
+                std::cmp::Ordering::Greater => break,
+            }
+        }
+        Ok(None)
+    }
+}
+
+// The size of the `FlushMode::Plain` record of a key, whose length is written plus one.
+fn plain_record_len(bytes: &[u8]) -> usize {
+    let mut len = bytes.len() as u64 + 1;
+    let mut varint_len = 1;
+    while len >= 0x80 {
+        len >>= 7;
+        varint_len += 1;
+    }
+    varint_len + bytes.len()
+}
+
+fn shared_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+pub(crate) fn write_varint<W: Write>(w: &mut W, mut value: u64) -> io::Result<()> {
+    let mut buf = [0; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    w.write_all(&buf[..len])
+}
+
+// Returns None if the reader is at its end before the first byte.
+pub(crate) fn read_varint<R: Read>(r: &mut R) -> io::Result<Option<u64>> {
+    let mut value = 0;
+    let mut shift = 0;
+    let mut byte = [0; 1];
+    loop {
+        if r.read(&mut byte)? == 0 {
+            return if shift == 0 { Ok(None) } else { Err(io::ErrorKind::UnexpectedEof.into()) };
+        }
+        if shift >= 64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "varint overflows u64"));
+        }
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+        shift += 7;
+    }
+}
+
+fn read_bytes<R: Read>(r: &mut R, len: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    r.take(len).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memtable::linkedlist_skiplist::seed_test_rng;
+    use crate::memtable::skiplist::SkipList;
+
+    fn flushed<const MAX_HEIGHT: usize>(list: &LinkedListSkipList<String, MAX_HEIGHT>, mode: FlushMode) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        list.flush(&mut bytes, mode).unwrap();
+        bytes
+    }
+
+    fn read_back(bytes: &[u8]) -> Vec<String> {
+        FlushReader::new(bytes)
+            .unwrap()
+            .map(|key| String::from_utf8(key.unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_delta_flush_round_trip() {
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for user in 0..50 {
+            for field in ["address", "email", "name", "phone"] {
+                list.insert(format!("tenants/limonite/users/{:08}/profile/{}", user, field));
+            }
+        }
+        let expected: Vec<String> = list.iter().cloned().collect();
+
+        let plain = flushed(&list, FlushMode::Plain);
+        let delta = flushed(&list, FlushMode::Delta);
+        assert_eq!(read_back(&plain), expected);
+        assert_eq!(read_back(&delta), expected);
+        // Neighbouring keys share 40 or more bytes.
+        assert!(delta.len() * 3 < plain.len(), "delta {} bytes, plain {} bytes", delta.len(), plain.len());
+    }
+
+    #[test]
+    fn test_flush_independent_of_insertion_order() {
+        seed_test_rng();
+        let keys: Vec<String> = (0..500).map(|key| format!("users/{:05}/name", key * 7)).collect();
+        let mut shuffled = keys.clone();
+        fastrand::shuffle(&mut shuffled);
+
+        let mut ascending: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for key in &keys {
+            ascending.insert(key.clone());
+        }
+        let mut descending: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for key in keys.iter().rev() {
+            descending.insert(key.clone());
+        }
+        // Inserting and removing extra keys leaves a differently shaped list with the same keys.
+        let mut churned: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for key in &shuffled {
+            churned.insert(key.clone());
+            churned.insert(format!("{}/removed", key));
+        }
+        for key in &keys {
+            assert!(churned.remove(&format!("{}/removed", key)));
+        }
+
+        for mode in [FlushMode::Plain, FlushMode::Delta] {
+            let expected = flushed(&ascending, mode);
+            assert_eq!(flushed(&descending, mode), expected);
+            assert_eq!(flushed(&churned, mode), expected);
+        }
+    }
+
+    #[test]
+    fn test_plain_record_len_matches_flush() {
+        // Lengths either side of each varint boundary, whose record length is written plus one.
+        for len in [0, 1, 126, 127, 128, 16_382, 16_383, 16_384] {
+            let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+            list.insert("k".repeat(len));
+            let bytes = flushed(&list, FlushMode::Plain);
+            let index_offset = u64::from_le_bytes(bytes[bytes.len() - FOOTER_LEN..].try_into().unwrap()) as usize;
+            // The records run from the header up to the end marker before the index.
+            let written = index_offset - 1 - (FLUSH_MAGIC.len() + 2);
+            assert_eq!(plain_record_len("k".repeat(len).as_bytes()), written, "key of {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn test_block_iter() {
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..300 {
+            list.insert(format!("{:0width$}", _i, width = 1 + _i % 40));
+        }
+        list.insert("x".repeat(200));
+        let expected: Vec<&String> = list.iter().collect();
+
+        for target in [1, 64, 100, 4096, 1 << 20] {
+            let blocks: Vec<Vec<&String>> = list.block_iter(target).collect();
+            assert!(blocks.iter().all(|block| !block.is_empty()));
+            for block in &blocks {
+                let block_bytes: usize = block.iter().map(|key| plain_record_len(key.as_bytes())).sum();
+                assert!(block_bytes <= target || block.len() == 1, "{} bytes in a block of {} keys against {}", block_bytes, block.len(), target);
+            }
+            // Each block is as full as it can be without going over the target.
+            for pair in blocks.windows(2) {
+                let block_bytes: usize = pair[0].iter().map(|key| plain_record_len(key.as_bytes())).sum();
+                assert!(block_bytes + plain_record_len(pair[1][0].as_bytes()) > target);
+            }
+            assert_eq!(blocks.concat(), expected);
+        }
+        assert_eq!(list.block_iter(1).count(), list.len());
+        assert_eq!(list.block_iter(1 << 20).count(), 1);
+        assert_eq!(plain_record_len(&[0; 126]), 127);
+        assert_eq!(plain_record_len(&[0; 127]), 129);
+        assert_eq!(plain_record_len(&[0; 128]), 130);
+
+        let empty: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert_eq!(empty.block_iter(64).count(), 0);
+    }
+
+    #[test]
+    fn test_flush_empty() {
+        let list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for mode in [FlushMode::Plain, FlushMode::Delta] {
+            let bytes = flushed(&list, mode);
+            // The header, the end of the records, an empty index and the footer.
+            assert_eq!(bytes.len(), FLUSH_MAGIC.len() + 2 + 1 + 1 + FOOTER_LEN);
+            assert_eq!(FlushReader::new(&bytes[..]).unwrap().mode(), mode);
+            assert!(read_back(&bytes).is_empty());
+            let table = FlushedTable::open(&bytes).unwrap();
+            assert_eq!(table.mode(), mode);
+            assert_eq!(table.get(b"").unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_flush_keys_without_shared_prefix() {
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for key in ["", "a", "ab", "b", "bcd", "c"] {
+            list.insert(key.to_string());
+        }
+        let expected: Vec<String> = list.iter().cloned().collect();
+        assert_eq!(read_back(&flushed(&list, FlushMode::Delta)), expected);
+    }
+
+    #[test]
+    fn test_truncated_flush() {
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        list.insert("truncated".to_string());
+        let bytes = flushed(&list, FlushMode::Delta);
+        let mut reader = FlushReader::new(&bytes[..FLUSH_MAGIC.len() + 2 + 4]).unwrap();
+        assert_eq!(reader.next().unwrap().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        // Cut just before the end of the records, which a whole key does not make up for.
+        let index_offset = u64::from_le_bytes(bytes[bytes.len() - FOOTER_LEN..].try_into().unwrap()) as usize;
+        let mut reader = FlushReader::new(&bytes[..index_offset - 1]).unwrap();
+        assert!(reader.next().unwrap().is_ok());
+        assert_eq!(reader.next().unwrap().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+        assert!(FlushReader::new(&[7_u8][..]).is_err());
+        assert!(FlushedTable::open(&bytes[..bytes.len() - 1]).is_err());
+
+        // Rewrite the index of a longer stream with entries reordered, so consecutive offsets or
+        // keys go backwards, or with an offset pointing into the header.
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..100 {
+            list.insert(format!("key/{:04}", _i));
+        }
+        let bytes = flushed(&list, FlushMode::Plain);
+        let index_offset = u64::from_le_bytes(bytes[bytes.len() - FOOTER_LEN..].try_into().unwrap()) as usize;
+        let table = FlushedTable::open(&bytes).unwrap();
+        assert!(table.index.len() >= 3);
+        let with_index = |index: &[(&[u8], usize)]| {
+            let mut corrupt = bytes[..index_offset].to_vec();
+            write_varint(&mut corrupt, index.len() as u64).unwrap();
+            for (key, offset) in index {
+                write_varint(&mut corrupt, key.len() as u64).unwrap();
+                corrupt.extend_from_slice(key);
+                write_varint(&mut corrupt, *offset as u64).unwrap();
+            }
+            corrupt.extend_from_slice(&(index_offset as u64).to_le_bytes());
+            corrupt
+        };
+        assert_eq!(FlushedTable::open(&with_index(&table.index)).unwrap().get(b"key/0042").unwrap(), Some(b"key/0042".to_vec()));
+        let mut swapped_offsets = table.index.clone();
+        let (first, second) = (swapped_offsets[1].1, swapped_offsets[2].1);
+        swapped_offsets[1].1 = second;
+        swapped_offsets[2].1 = first;
+        let mut swapped_keys = table.index.clone();
+        let (first, second) = (swapped_keys[1].0, swapped_keys[2].0);
+        swapped_keys[1].0 = second;
+        swapped_keys[2].0 = first;
+        let mut in_header = table.index.clone();
+        in_header[0].1 = FLUSH_MAGIC.len();
+        for corrupt in [swapped_offsets, swapped_keys, in_header] {
+            let error = FlushedTable::open(&with_index(&corrupt)).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_flush_encoded() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i64, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..500 {
+            list.insert(fastrand::i64(-1_000_000..1_000_000));
+        }
+        for mode in [FlushMode::Plain, FlushMode::Delta] {
+            let mut bytes = Vec::new();
+            list.flush_encoded(&mut bytes, mode).unwrap();
+            let encoded: Vec<Vec<u8>> = FlushReader::new(&bytes[..]).unwrap().map(Result::unwrap).collect();
+            assert!(encoded.windows(2).all(|pair| pair[0] < pair[1]));
+            let decoded: Vec<i64> = encoded.iter().map(|key| i64::decode(&mut &key[..]).unwrap()).collect();
+            assert!(decoded.iter().eq(list.iter()));
+        }
+    }
+
+    #[test]
+    fn test_flush_header() {
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        list.insert("versioned".to_string());
+        let bytes = flushed(&list, FlushMode::Plain);
+        assert_eq!(bytes[..4], FLUSH_MAGIC);
+        assert_eq!(bytes[4], FLUSH_VERSION);
+        assert_eq!(read_back(&bytes), vec!["versioned".to_string()]);
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        let error = FlushReader::new(&wrong_magic[..]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("magic"), "{}", error);
+
+        let mut future = bytes.clone();
+        future[4] = FLUSH_VERSION + 1;
+        let error = FlushReader::new(&future[..]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains(&format!("version {}", FLUSH_VERSION + 1)), "{}", error);
+    }
+
+    #[test]
+    fn test_flushed_table_get() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(format!("users/{:06}/name", fastrand::u32(..500_000) * 2));
+        }
+        let keys: Vec<String> = list.iter().cloned().collect();
+        for mode in [FlushMode::Plain, FlushMode::Delta] {
+            let bytes = flushed(&list, mode);
+            assert_eq!(read_back(&bytes), keys);
+            let table = FlushedTable::open(&bytes).unwrap();
+            assert_eq!(table.index.len(), keys.len().div_ceil(SPARSE_INDEX_INTERVAL));
+            for _i in 0..200 {
+                let key = &keys[fastrand::usize(..keys.len())];
+                assert_eq!(table.get(key.as_bytes()).unwrap().as_deref(), Some(key.as_bytes()));
+            }
+            for key in [&keys[0], &keys[SPARSE_INDEX_INTERVAL - 1], &keys[SPARSE_INDEX_INTERVAL], &keys[keys.len() - 1]] {
+                assert_eq!(table.get(key.as_bytes()).unwrap().as_deref(), Some(key.as_bytes()));
+            }
+            // Odd ids are never inserted, so fall between stored keys.
+            for missing in ["a", "users/000001/name", "users/999999/name", "users/500001/name", "z"] {
+                assert_eq!(table.get(missing.as_bytes()).unwrap(), None, "{}", missing);
+            }
+            assert_eq!(table.get(format!("{}/suffix", keys[keys.len() - 1]).as_bytes()).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_flush_version_1() {
+        // A stream written before the sparse index: no end of the records, index or footer.
+        let mut bytes = FLUSH_MAGIC.to_vec();
+        bytes.extend([1, FlushMode::Plain.tag()]);
+        for key in ["a", "bc"] {
+            write_varint(&mut bytes, key.len() as u64).unwrap();
+            bytes.extend(key.as_bytes());
+        }
+        assert_eq!(read_back(&bytes), vec!["a".to_string(), "bc".to_string()]);
+        let error = FlushedTable::open(&bytes).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("no index"), "{}", error);
+    }
+
+    #[test]
+    fn test_flush_mmap() {
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(format!("key/{:05}", _i * 7));
+        }
+        let expected: Vec<String> = list.iter().cloned().collect();
+        let empty: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let path = std::env::temp_dir().join(format!("limonitedb-flush-mmap-{}", std::process::id()));
+        for mode in [FlushMode::Plain, FlushMode::Delta] {
+            list.flush_mmap(&path, mode).unwrap();
+            let bytes = std::fs::read(&path).unwrap();
+            assert_eq!(bytes, flushed(&list, mode));
+            assert_eq!(read_back(&bytes), expected);
+            let table = FlushedTable::open(&bytes).unwrap();
+            assert_eq!(table.get(b"key/00007").unwrap().as_deref(), Some(&b"key/00007"[..]));
+            assert_eq!(table.get(b"key/00008").unwrap(), None);
+
+            empty.flush_mmap(&path, mode).unwrap();
+            let reader = FlushReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+            assert_eq!(reader.mode(), mode);
+            assert_eq!(reader.count(), 0);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    // A write to a user key encoded as `user_key:op`, ordered by the user key alone.
+    #[derive(Default)]
+    struct Record(Vec<u8>);
+
+    impl Record {
+        fn new(user_key: &str, op: &str) -> Self {
+            Record(format!("{}:{}", user_key, op).into_bytes())
+        }
+
+        fn user_key(&self) -> &[u8] {
+            self.0.split(|byte| *byte == b':').next().unwrap()
+        }
+    }
+
+    impl AsRef<[u8]> for Record {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl PartialEq for Record {
+        fn eq(&self, other: &Self) -> bool {
+            self.user_key() == other.user_key()
+        }
+    }
+
+    impl Eq for Record {}
+
+    impl PartialOrd for Record {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Record {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.user_key().cmp(other.user_key())
+        }
+    }
+
+    #[test]
+    fn test_flush_merged() {
+        let mut oldest: LinkedListSkipList<Record, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut middle: LinkedListSkipList<Record, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut newest: LinkedListSkipList<Record, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for user_key in ["a", "b", "c", "d"] {
+            oldest.insert(Record::new(user_key, "put1"));
+        }
+        for user_key in ["b", "c", "e"] {
+            middle.insert(Record::new(user_key, "put2"));
+        }
+        newest.insert(Record::new("c", "delete"));
+        newest.insert(Record::new("e", "put3"));
+        newest.insert(Record::new("f", "delete"));
+
+        let expected = ["a:put1", "b:put2", "c:delete", "d:put1", "e:put3", "f:delete"];
+        for mode in [FlushMode::Plain, FlushMode::Delta] {
+            let mut bytes = Vec::new();
+            LinkedListSkipList::flush_merged(&[&newest, &middle, &oldest], &mut bytes, mode).unwrap();
+            assert_eq!(read_back(&bytes), expected);
+        }
+        let mut bytes = Vec::new();
+        LinkedListSkipList::<Record, { 2_usize.pow(6) }>::flush_merged(&[], &mut bytes, FlushMode::Plain).unwrap();
+        assert!(read_back(&bytes).is_empty());
+    }
+}
@@ -1,382 +1,4649 @@
-use std::cmp::{max};
-use std::fmt::Display;
+use std::alloc::Layout;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt::{Debug, Display, Formatter};
 use std::marker::PhantomData;
+use std::ops::{Bound, ControlFlow, RangeBounds};
 use std::ptr::{NonNull};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::OnceLock;
 
-use crate::memtable::skiplist::{SkipList, SkipListIterator};
+use crate::memtable::allocator::{Arena, Global, NodeAllocator};
+use crate::memtable::skiplist::{Clock, Distance, IdentityExtractor, MetricsSink, PrefixExtractor, SeekError, SkipList, SkipListError, SkipListIterator, SystemClock};
 
-pub struct LinkedListSkipList<'a, Key: Ord + Display + Default, const MAX_HEIGHT: usize> where
+// About the most nodes `prefetch` touches, few enough for their towers to stay in cache together.
+const PREFETCH_NODES: usize = 16 * 1024;
+
+// The most freed nodes of each height kept for reuse, so a burst of removals does not pin memory.
+const FREE_NODES_PER_HEIGHT: usize = 64;
+
+/// Environment variable which, set to a u64, seeds every list not given a seed by `with_seed`, so
+/// a run's towers can be reproduced.
+pub const SEED_ENV_VAR: &str = "LIMONITEDB_SEED";
+
+// Seed used by tests when `SEED_ENV_VAR` is unset, so their lists are laid out the same every run.
+#[cfg(test)]
+const TEST_SEED: u64 = 0x5eed;
+
+// the seed set through `SEED_ENV_VAR`, read once per process.
+fn env_seed() -> Option<u64> {
+    static SEED: OnceLock<Option<u64>> = OnceLock::new();
+    *SEED.get_or_init(|| std::env::var(SEED_ENV_VAR).ok().and_then(|seed| seed.trim().parse().ok()))
+}
+
+// the generator for a list not given a seed.
+fn default_rng() -> fastrand::Rng {
+    let seed = env_seed();
+    #[cfg(test)]
+    let seed = seed.or(Some(TEST_SEED));
+    seed.map_or_else(fastrand::Rng::new, fastrand::Rng::with_seed)
+}
+
+// Seeds the calling thread's global `fastrand` generator, which tests draw their keys from, as
+// `default_rng` seeds lists, so a failing run can be replayed with the same `SEED_ENV_VAR`.
+#[cfg(test)]
+pub(crate) fn seed_test_rng() {
+    fastrand::seed(env_seed().unwrap_or(TEST_SEED));
+}
+
+/// A list whose nodes hold boxed keys, for keys which are not `Sized` such as byte strings of
+/// any length. Look keys up with `get` using the unboxed form.
+pub type BoxedSkipList<'a, Key, const MAX_HEIGHT: usize> = LinkedListSkipList<'a, Box<Key>, MAX_HEIGHT>;
+
+/// When nodes are given their height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromotionStrategy {
+    /// Each node is given a random height when inserted and keeps it.
+    Eager,
+    /// As `Eager`, but each `access` of a key may also raise its node a level, so frequently read
+    /// keys grow taller and are reached in fewer steps.
+    AccessAdaptive,
+}
+
+/// Which way `get_bound` looks from its bound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Towards larger keys.
+    Forward,
+    /// Towards smaller keys.
+    Backward,
+}
+
+/// A sorted set of keys in a skip list. Keys must be totally ordered with an `Ord` which agrees
+/// with their `PartialEq`, keys comparing `Equal` being `==`. Searches made in debug builds assert
+/// this for the keys they compare.
+pub struct LinkedListSkipList<'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator = Global> where
     Key: Ord,
 {
+    alloc: A,
     head: NonNull<Node<Key>>,
-    current_height: usize,
-    current_size: usize,
-    _marker: PhantomData<&'a Key>,
+    // Atomic so views shared between threads can read them. Writes need `&mut self`, so no read
+    // can race one, and they update through `get_mut` rather than atomic read-modify-writes. Reads
+    // are ordered after the last write by whatever handed the list over, so load `Relaxed`.
+    current_height: AtomicUsize,
+    current_size: AtomicUsize,
+    prefix_extractor: Option<Box<dyn PrefixExtractor<Key>>>,
+    metrics: Option<Box<dyn MetricsSink>>,
+    clock: Box<dyn Clock>,
+    cache_comparisons: bool,
+    read_repair: bool,
+    promotion: PromotionStrategy,
+    // Last node on each level with its rank, cached by an insert which lands at the end of the
+    // list so runs of appends skip the search. Cleared whenever links are changed any other way.
+    // Boxed so an append moves a pointer rather than copying both arrays.
+    tails: Option<Box<Path<Key, MAX_HEIGHT>>>,
+    // Search path reused by writes so each only fills the levels in use, taken while a write
+    // searches. Every level above those the last search filled holds the head at rank 0.
+    path: Option<Box<Path<Key, MAX_HEIGHT>>>,
+    append_hint: bool,
+    // Unlinked nodes kept for reuse by inserts, indexed by height - 1. Their keys have been moved
+    // out so must not be dropped.
+    free_nodes: Vec<Vec<NonNull<Node<Key>>>>,
+    // Bumped by every change to the links, so a cursor can tell its position may have been freed.
+    generation: usize,
+    // Picks the heights of new nodes.
+    rng: fastrand::Rng,
+    _marker: PhantomData<(&'a (), Key)>,
 }
 
-impl<'a, Key: Ord + Display + Default, const MAX_HEIGHT: usize> LinkedListSkipList<'a, Key, MAX_HEIGHT> {
-    fn new() -> Self {
+impl<'a, Key: Ord + Default, const MAX_HEIGHT: usize> LinkedListSkipList<'a, Key, MAX_HEIGHT> {
+    pub fn new() -> Self {
+        Self::new_in(Global)
+    }
+}
+
+impl<'a, Key: Ord + Default, const MAX_HEIGHT: usize> LinkedListSkipList<'a, Key, MAX_HEIGHT, Arena> {
+    /// Creates a list backed by an arena with room for `expected_elements` nodes up front, so
+    /// loading that many keys does not grow the arena. Towers are allocated separately from nodes
+    /// so do not count towards the reservation.
+    pub fn with_capacity(expected_elements: usize) -> Self {
+        let node_size = Layout::new::<Node<Key>>().size();
+        Self::new_in(Arena::with_capacity((expected_elements + 1) * node_size)) // One more for the head.
+    }
+}
+
+impl<'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {
+    /// Creates a list whose nodes are allocated from `alloc`.
+    pub fn new_in(alloc: A) -> Self {
         Self {
-            head: Node::new_head(MAX_HEIGHT),
-            current_height: 0,
-            current_size: 0,
+            head: Node::new_head(MAX_HEIGHT, &alloc),
+            alloc,
+            current_height: AtomicUsize::new(0),
+            current_size: AtomicUsize::new(0),
+            prefix_extractor: None,
+            metrics: None,
+            clock: Box::new(SystemClock),
+            cache_comparisons: false,
+            read_repair: false,
+            promotion: PromotionStrategy::Eager,
+            tails: None,
+            path: None,
+            append_hint: true,
+            free_nodes: Vec::new(),
+            generation: 0,
+            rng: default_rng(),
             _marker: PhantomData,
         }
     }
 
-    // find the node that is closest in value but less then.
-    fn find_equal_or_less_then(&self, key: &Key) -> (Link<Key>, [NonNull<Node<Key>>; MAX_HEIGHT]) {
-        let mut previous: [NonNull<Node<Key>>; MAX_HEIGHT] = std::array::from_fn(|_| self.head);
+    /// Sets the extractor consulted by prefix scans. Without one the whole key is the prefix.
+    pub fn with_prefix_extractor(mut self, extractor: Box<dyn PrefixExtractor<Key>>) -> Self {
+        self.prefix_extractor = Some(extractor);
+        self
+    }
+
+    /// Sets the sink which is told the cost of every point lookup and seek. Without one no costs
+    /// are reported.
+    pub fn with_metrics(mut self, metrics: Box<dyn MetricsSink>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Sets whether point lookups and seeks remember the node which sent them down a level. The
+    /// level below usually reaches the same node, which is then known to be greater than the
+    /// target without comparing it again. Worth enabling for keys which are expensive to compare.
+    pub fn with_comparison_cache(mut self, enabled: bool) -> Self {
+        self.cache_comparisons = enabled;
+        self
+    }
+
+    /// Sets whether searches which hold the list mutably, such as those of `access` and `remove`,
+    /// link back in any node they step over which is missing from a level its tower reaches.
+    /// Each repair is reported to the metrics sink. Lookups through a shared reference never
+    /// write, so they may run alongside other readers.
+    pub fn with_read_repair(mut self, enabled: bool) -> Self {
+        self.read_repair = enabled;
+        self
+    }
+
+    /// Sets whether an insert greater than every key, following an insert which also landed at
+    /// the end, is linked after the cached last node of each level without searching. Keeps
+    /// ascending inserts from paying a search from the head each. Enabled by default.
+    pub fn with_append_hint(mut self, enabled: bool) -> Self {
+        self.append_hint = enabled;
+        self
+    }
+
+    /// Seeds the generator which picks the height of each new node, so the same writes build the
+    /// same towers every run. Without it the seed is taken from `SEED_ENV_VAR` if set, otherwise
+    /// chosen at random.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = fastrand::Rng::with_seed(seed);
+        self
+    }
+
+    /// Sets when nodes are given their height. Defaults to `PromotionStrategy::Eager`.
+    pub fn with_promotion(mut self, promotion: PromotionStrategy) -> Self {
+        self.promotion = promotion;
+        self
+    }
+
+    /// Sets the clock keys inserted with a ttl expire against. Defaults to `SystemClock`.
+    pub fn with_clock(mut self, clock: Box<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Returns an iterator over every key in the list in ascending order.
+    pub fn iter(&self) -> Iter<'_, Key> {
+        unsafe { self.iter_at(self.head_next(0)) }
+    }
+
+    /// Returns an iterator over the keys a reader at `snapshot_seq` sees at time `now`, in
+    /// ascending order. A single walk of the bottom level skips keys whose ttl has passed by
+    /// `now`, keys `seq` numbers above `snapshot_seq` and keys `is_tombstone` marks as deletes.
+    pub fn iter_visible<S, T>(&self, snapshot_seq: u64, now: u64, seq: S, is_tombstone: T) -> VisibleIter<'_, Key, S, T>
+    where
+        S: Fn(&Key) -> u64,
+        T: Fn(&Key) -> bool,
+    {
+        VisibleIter {
+            current: unsafe { self.head_next(0) },
+            snapshot_seq,
+            now,
+            seq,
+            is_tombstone,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Folds every key in ascending order into an accumulator, walking the bottom level directly
+    /// rather than through an iterator.
+    pub fn fold<B, F: FnMut(B, &Key) -> B>(&self, init: B, mut f: F) -> B {
+        let mut accumulator = init;
         unsafe {
-            // 1. Case where node is the smallest or other nodes exist in the tree
-            if self.current_height == 0 || (*self.head_next(0).unwrap().as_ptr()).key > *key {
-                return (None, previous);
-            }
-            // 2. Search the rest of the list.
-            let mut search_level = self.current_height - 1;
-            let mut current_node = self.head;
-            loop {
-                previous[search_level] = current_node;
-                match (*current_node.as_ptr()).next(search_level) {
-                    None => {
-                        if search_level == 0 {
-                            return (None, previous);
-                        }
-                        search_level -= 1;
-                    }
-                    Some(next_node) => {
-                        if (*next_node.as_ptr()).key >= *key {
-                            if (*next_node.as_ptr()).key == *key {
-                                return (Some(next_node), previous);
-                            } else if search_level == 0 {
-                                return (None, previous);
-                            }
-                            search_level -= 1;
-                        } else {
-                            current_node = next_node;
-                        }
-                    }
-                };
+            let mut current = self.head_next(0);
+            while let Some(node) = current {
+                accumulator = f(accumulator, &(*node.as_ptr()).key);
+                current = (*node.as_ptr()).next(0);
             }
         }
+        accumulator
     }
 
-    // find the node that is equal or closest greatest value. Useful for iteration.
-    fn find_equal_or_greater_then(&self, key: &Key) -> Link<Key> {
+    /// Calls `f` on each key from the first key >= `start` in ascending order until it returns
+    /// `Break(b)`, returning `Some(b)`. Returns None if the keys run out first.
+    pub fn scan_while<B, F: FnMut(&Key) -> ControlFlow<B>>(&self, start: &Key, mut f: F) -> Option<B> {
+        let mut current = self.find_first_not_before(|key| key < start);
         unsafe {
-            // 1. Case where node is the smallest or other nodes exist in the tree
-            if self.current_height == 0 || (*self.head_next(0).unwrap().as_ptr()).key > *key {
-                return None;
-            }
-            // 2. Search the rest of the list.
-            let mut search_level = self.current_height - 1;
-            let mut current_node = self.head;
-            loop {
-                match (*current_node.as_ptr()).next(search_level) {
-                    None => {
-                        if search_level == 0 {
-                            return None;
-                        }
-                        search_level -= 1;
-                    }
-                    Some(next_node) => {
-                        if (*next_node.as_ptr()).key >= *key {
-                            if (*next_node.as_ptr()).key == *key || search_level == 0 {
-                                return Some(next_node);
-                            }
-                            search_level -= 1;
-                        } else {
-                            current_node = next_node;
-                        }
-                    }
-                };
+            while let Some(node) = current {
+                if let ControlFlow::Break(b) = f(&(*node.as_ptr()).key) {
+                    return Some(b);
+                }
+                current = (*node.as_ptr()).next(0);
             }
         }
+        None
     }
 
-    #[inline(always)]
-    fn get_max_height(&self) -> usize {
-        self.current_height
+    /// Walks the top levels, pulling the towers every search passes through into the cache so
+    /// the first lookups after a load do not pay for the misses. Stops before a level would take
+    /// the walk past `PREFETCH_NODES` nodes, as lower levels would evict the upper ones. Only
+    /// hints the cache, the list is unchanged.
+    pub fn prefetch(&self) {
+        let mut visited = 0;
+        unsafe {
+            for level in (0..self.height()).rev() {
+                // Each level holds about twice the nodes of the level above.
+                if visited * 2 > PREFETCH_NODES {
+                    return;
+                }
+                let mut current = self.head_next(level);
+                while let Some(node) = current {
+                    prefetch_read(node.as_ptr());
+                    let node = &*node.as_ptr();
+                    prefetch_read(node.links.as_ptr());
+                    current = node.next(level);
+                    visited += 1;
+                }
+            }
+        }
     }
 
-    #[inline(always)]
-    fn random_height(&self) -> usize {
-        let mut height = 1;
-        while height < MAX_HEIGHT && fastrand::bool() {
-            height += 1
-        }
-        height
+    /// Returns the key nearest `bound` in `direction`, such as the smallest key greater than `k`
+    /// for `(Excluded(&k), Forward)` or the largest key at most `k` for `(Included(&k), Backward)`.
+    /// An unbounded bound gives the smallest key going forward and the largest going backward.
+    pub fn get_bound(&self, bound: Bound<&Key>, direction: Direction) -> Option<&Key> {
+        let node = match (bound, direction) {
+            (Bound::Included(bound), Direction::Forward) => self.find_first_not_before(|key| key < bound),
+            (Bound::Excluded(bound), Direction::Forward) => self.find_first_not_before(|key| key <= bound),
+            (Bound::Unbounded, Direction::Forward) => unsafe { self.head_next(0) },
+            (Bound::Included(bound), Direction::Backward) => Some(self.find_last_before(|key| key <= bound)),
+            (Bound::Excluded(bound), Direction::Backward) => Some(self.find_last_before(|key| key < bound)),
+            (Bound::Unbounded, Direction::Backward) => Some(self.find_last_before(|_| true)),
+        };
+        node.filter(|node| !self.is_head(*node)).map(|node| unsafe { &(*node.as_ptr()).key })
     }
 
-    #[inline(always)]
-    unsafe fn head_next(&self, level: usize) -> Link<Key> {
-        (*self.head.as_ptr()).next(level)
+    /// Returns up to `n - 1` keys splitting the list into `n` contiguous shards of nearly equal
+    /// counts, for scanning in parallel. Shard `i` holds the keys from `split[i - 1]` up to but not
+    /// including `split[i]`, the first shard starting at the smallest key and the last running to
+    /// the largest. Each split key is found from the spans in O(log n). A list of fewer than `n`
+    /// keys gives one shard per key.
+    pub fn split_points(&self, n: usize) -> Vec<&Key> {
+        let len = self.len();
+        let mut points: Vec<&Key> = Vec::with_capacity(n.saturating_sub(1));
+        for shard in 1..n {
+            // The first key of each shard, by its zero based position. Splitting at the smallest key
+            // would leave the first shard empty.
+            let position = shard * len / n;
+            let Some(node) = self.node_at_rank(position + 1).filter(|_| position > 0) else {
+                continue;
+            };
+            let key = unsafe { &(*node.as_ptr()).key };
+            if points.last() != Some(&key) {
+                points.push(key);
+            }
+        }
+        points
     }
 
-    #[inline(always)]
-    unsafe fn head_set_next(&self, level: usize, node: Link<Key>) {
-        (*self.head.as_ptr()).set_next(level, node)
+    /// Returns clones of the keys in `[start, end)` in ascending order, owned so they can be
+    /// handed to another thread once the list is released. The Vec is sized up front from the
+    /// ranks of the two bounds, so it is never reallocated. Expired keys are included as they are
+    /// by `iter`.
+    pub fn range_owned(&self, start: &Key, end: &Key) -> Vec<Key>
+    where
+        Key: Clone,
+    {
+        let (previous, start_rank) = self.find_last_before_ranked(|key| key < start);
+        let (_, end_rank) = self.find_last_before_ranked(|key| key < end);
+        let count = end_rank.saturating_sub(start_rank);
+        let mut keys = Vec::with_capacity(count);
+        unsafe {
+            keys.extend(self.iter_at((*previous.as_ptr()).next(0)).take(count).cloned());
+        }
+        keys
     }
 
-    fn print(&self) {
-        for i in (0..self.current_height).rev() {
-            unsafe {
-                let mut next_node = (*self.head.as_ptr()).next(i);
-                loop {
-                    match next_node {
-                        Some(node) => {
-                            print!("-> [ {} ]", (*node.as_ptr()).key);
-                            next_node = (*node.as_ptr()).next(i);
-                        }
-                        None => {
-                            println!("-> None");
-                            break;
-                        }
+    // finds the node at `rank`, following the spans down from the top level. The head is rank 0.
+    fn node_at_rank(&self, rank: usize) -> Link<Key> {
+        if rank == 0 || rank > self.len() {
+            return None;
+        }
+        unsafe {
+            let mut current_node = self.head;
+            let mut current_rank = 0;
+            for level in (0..self.height()).rev() {
+                while let Some(next_node) = (*current_node.as_ptr()).next(level) {
+                    let span = (*current_node.as_ptr()).span(level);
+                    if current_rank + span > rank {
+                        break;
                     }
+                    current_rank += span;
+                    current_node = next_node;
                 }
             }
+            Some(current_node)
         }
     }
-}
 
-impl<'a, Key: Ord + Display + Default, const MAX_HEIGHT: usize> SkipList<Key> for LinkedListSkipList<'a, Key, MAX_HEIGHT>
-{
-    fn insert(&mut self, key: Key) {
-        let (node, previous) = self.find_equal_or_less_then(&key); // This must run so self.previous is populated
-        unsafe {
-            // 1.
-            match node {
-                Some(node) => {
-                    (*node.as_ptr()).key = key;
-                }
-                None => {
-                    let height = self.random_height();
-                    let node = Node::new_link(key, height);
-                    for i in 0..height {
-                        let previous_node = previous[i].as_ptr();
-                        (*node.as_ptr()).set_next(i, (*previous_node).next(i));
-                        (*previous_node).set_next(i, Some(node))
-                    }
-                    self.current_height = max(self.current_height, height);
-                    self.current_size += 1
+    /// Ends writes to the list and packs its top `index_levels` levels into a sorted array of
+    /// their keys, which searches binary search before following links down the remaining levels.
+    /// The upper levels are then read from contiguous memory rather than from nodes scattered
+    /// across the heap. Packing every level leaves a plain binary search. The index holds a clone
+    /// of each key on the lowest packed level, about `len / 2^(height - index_levels)` keys.
+    pub fn freeze(self, index_levels: usize) -> FrozenSkipList<'a, Key, MAX_HEIGHT, A>
+    where
+        Key: Clone,
+    {
+        let index_level = self.height().saturating_sub(index_levels);
+        let mut index_keys = Vec::new();
+        let mut index_nodes = Vec::new();
+        if index_level < self.height() {
+            unsafe {
+                let mut current = self.head_next(index_level);
+                while let Some(node) = current {
+                    index_keys.push((*node.as_ptr()).key.clone());
+                    index_nodes.push(node);
+                    current = (*node.as_ptr()).next(index_level);
                 }
             }
         }
+        FrozenSkipList {
+            list: self,
+            index_keys,
+            index_nodes,
+            index_level,
+        }
     }
 
-    fn contains(&self, key: &Key) -> bool {
-        let (node, _) = self.find_equal_or_less_then(key);
-        node.is_some()
+    /// Returns a read only view of the list which can be shared with other threads.
+    pub fn view(&self) -> ImmutableView<'_, 'a, Key, MAX_HEIGHT, A> {
+        ImmutableView { list: self }
     }
 
-    fn estimate_count(&self, _key: &Key) -> usize {
-        self.current_size
+    /// Returns a cursor over the list positioned before the first key.
+    pub fn cursor(&self) -> SkipListCursor<'_, 'a, Key, MAX_HEIGHT, A> {
+        SkipListCursor {
+            list: self,
+            state: CursorState::new(Some(self.head), 0),
+            generation: self.generation,
+        }
     }
-}
 
-impl<'a, Key: Ord + Display + Default, const MAX_HEIGHT: usize> Drop for LinkedListSkipList<'a, Key, MAX_HEIGHT> {
-    fn drop(&mut self) {
-        unsafe {
-            // Start from the head of the list  // Iterate over each node and deallocate it
-            let mut current_node = self.head_next(0);
-            while let Some(node) = current_node {
-                current_node = (*node.as_ptr()).next(0);
-                drop(Box::from_raw(node.as_ptr()));
-            }
-            drop(Box::from_raw(self.head.as_ptr())); // deallocate the head node
+    /// Returns a cursor positioned just before the first key within `start`, so the first call to
+    /// `next` yields that key. `Bound::Unbounded` is the same as `cursor`.
+    pub fn cursor_from(&self, start: Bound<&Key>) -> SkipListCursor<'_, 'a, Key, MAX_HEIGHT, A> {
+        let (previous, rank) = match start {
+            Bound::Included(start) => self.find_last_before_ranked(|key| key < start),
+            Bound::Excluded(start) => self.find_last_before_ranked(|key| key <= start),
+            Bound::Unbounded => (self.head, 0),
+        };
+        SkipListCursor {
+            list: self,
+            state: CursorState::new(Some(previous), rank),
+            generation: self.generation,
         }
     }
-}
 
-impl<'a, Key: Ord + Display + Default, const MAX_HEIGHT: usize> IntoIterator for LinkedListSkipList<'a, Key, MAX_HEIGHT>
-    where
-        Key: Ord,
-{
-    type Item = &'a Key;
-    type IntoIter = LinkedListSkipListIterator<'a, Key, MAX_HEIGHT>;
+    /// Returns a handle to the node of the first key >= `key`, for callers which walk or annotate
+    /// nodes directly. See `NodeRef` for what keeps a handle usable.
+    pub fn seek_node(&self, key: &Key) -> Option<NodeRef<Key>> {
+        self.find_equal_or_greater_then(key).map(|(node, _)| NodeRef { node })
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        LinkedListSkipListIterator {
-            current: Some(self.head),
-            skip_list: self,
-        }
+    /// Returns a handle to the node of the smallest key.
+    pub fn min_node(&self) -> Option<NodeRef<Key>> {
+        unsafe { self.head_next(0).map(|node| NodeRef { node }) }
     }
-}
 
-pub struct LinkedListSkipListIterator<'a, Key: Ord + Display + Default, const MAX_HEIGHT: usize>
-    where
-        Key: Ord,
-{
-    skip_list: LinkedListSkipList<'a, Key, MAX_HEIGHT>,
-    current: Link<Key>,
-}
+    /// Returns a handle to the node of the largest key.
+    pub fn max_node(&self) -> Option<NodeRef<Key>> {
+        let last = self.find_last_before(|_| true);
+        (!self.is_head(last)).then_some(NodeRef { node: last })
+    }
 
+    // Caller must ensure `front` is a node of this list or None.
+    unsafe fn iter_at(&self, front: Link<Key>) -> Iter<'_, Key> {
+        Iter::new(front, self.head, self.height())
+    }
 
-impl<'a, Key: Ord + Default + Display, const MAX_HEIGHT: usize> Iterator for LinkedListSkipListIterator<'a, Key, MAX_HEIGHT> {
-    type Item = &'a Key;
-    fn next(&mut self) -> Option<Self::Item> {
-        unsafe {
-            let next_node =  (*self.current.unwrap().as_ptr()).links[0];
-            return match next_node {
-                Some(next_node_val) => {
-                    self.current = next_node;
-                    let next_key = &next_node_val.as_ref().key;
-                    Some(next_key)
-                },
-                None => None
-            };
-        }
+    /// Returns an iterator over the keys starting at the first key for which `is_before` returns
+    /// false. `is_before` must hold for a (possibly empty) run of the smallest keys only.
+    pub(crate) fn iter_from_by<F: Fn(&Key) -> bool>(&self, is_before: F) -> Iter<'_, Key> {
+        unsafe { self.iter_at(self.find_first_not_before(is_before)) }
     }
-}
 
-impl<'a, Key: Ord + Default + Display, const MAX_HEIGHT: usize> SkipListIterator<&'a Key> for LinkedListSkipListIterator<'a, Key, MAX_HEIGHT>
-{
-    fn valid(&self) -> bool {
-        self.current.is_some()
+    /// Returns the first key for which `is_before` returns false for in place modification. The
+    /// caller must not change how the key orders relative to the other keys in the list.
+    pub(crate) fn first_not_before_mut<F: Fn(&Key) -> bool>(&mut self, is_before: F) -> Option<&mut Key> {
+        unsafe { self.find_first_not_before(is_before).map(|node| &mut (*node.as_ptr()).key) }
     }
 
-    fn key(&self) -> Option<&'a Key> {
+    /// Returns the bytes taken by the list's nodes including the head, not counting their towers
+    /// or memory the keys hold elsewhere. Replacing a key does not change it. An expired key is
+    /// counted, as it is by `len`, until `access` or a removal unlinks it.
+    pub fn memory_usage(&self) -> usize {
+        (self.len() + 1) * Layout::new::<Node<Key>>().size()
+    }
+
+    /// Returns every byte of heap memory the list holds, for seeing its overhead next to
+    /// `memory_usage`: the nodes including the head and those kept for reuse, the links and spans
+    /// of every tower, the free list itself and the search path reused by writes. With an
+    /// allocator which pools memory, such as an `Arena`, the whole pool is counted in place of the
+    /// nodes, so slack in it shows up too.
+    /// Walks every node, costing O(n).
+    pub fn total_memory(&self) -> usize {
+        let node_size = Layout::new::<Node<Key>>().size();
+        let tower_bytes = |node: NonNull<Node<Key>>| unsafe {
+            let node = &*node.as_ptr();
+            node.links.capacity() * std::mem::size_of::<Link<Key>>() + node.spans.capacity() * std::mem::size_of::<usize>()
+        };
+        let mut nodes = 0;
+        let mut towers = 0;
         unsafe {
-            return match self.current.as_ref() {
-                Some(current) =>  Some(&current.as_ref().key),
-                None => None
+            let mut current = Some(self.head);
+            while let Some(node) = current {
+                nodes += 1;
+                towers += tower_bytes(node);
+                current = (*node.as_ptr()).next(0);
             }
         }
+        for node in self.free_nodes.iter().flatten() {
+            nodes += 1;
+            towers += tower_bytes(*node);
+        }
+        let free_list = self.free_nodes.capacity() * std::mem::size_of::<Vec<NonNull<Node<Key>>>>()
+            + self.free_nodes.iter().map(|free| free.capacity() * std::mem::size_of::<NonNull<Node<Key>>>()).sum::<usize>();
+        let path = (self.path.iter().count() + self.tails.iter().count()) * std::mem::size_of::<Path<Key, MAX_HEIGHT>>();
+        let node_bytes = self.alloc.reserved_bytes().unwrap_or(nodes * node_size);
+        node_bytes + towers + free_list + path
     }
 
-    fn advance(&mut self) {
-        unsafe {
-            let next_node =  (*self.current.unwrap().as_ptr()).links[0];
-            match next_node {
-                Some(_) => {
-                    self.current = next_node;
-                },
-                None => ()
-            };
-        }
+    /// Returns the number of keys in the list.
+    pub fn len(&self) -> usize {
+        self.current_size.load(AtomicOrdering::Relaxed)
     }
 
-    fn prev(&mut self) {
-        unimplemented!() // Requires backward links or a stack to track history
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
-    fn seek(&mut self, _target: &Key) {
-        let target_node = self.skip_list.find_equal_or_greater_then(_target);
-        match target_node {
-            Some(_) => {
-                self.current = target_node;
-            },
-            None => () //TODO: return a error/option if our key is less then all values
-        }
+    /// Inserts `key`, returning the equal key it replaced if there was one.
+    pub fn replace(&mut self, key: Key) -> Option<Key> {
+        self.insert_if(key, |_| true).unwrap_or_else(|_| unreachable!())
     }
 
-    fn seek_for_prev(&mut self, _target: &Key) {
-        unimplemented!() // Requires backward links or additional tracking
+    /// Inserts `key` so that it expires `ttl` ticks of the list's clock from now, returning the
+    /// equal live key it replaced if there was one. Expired keys are treated as absent by
+    /// `contains` and `access`, though they are still counted by `len` and seen by iterators until
+    /// `access` reclaims them.
+    pub fn insert_with_ttl(&mut self, key: Key, ttl: u64) -> Option<Key> {
+        let expires_at = self.clock.now().saturating_add(ttl);
+        self.write_if(key, Some(expires_at), None, |_| true).unwrap_or_else(|_| unreachable!())
     }
 
-    fn seek_to_first(&mut self) {
-        self.current = Some(self.skip_list.head);
+    /// Inserts `key` if `should_write` accepts the equal key currently in the list (None when
+    /// absent), using a single search. Returns the replaced key on a write, otherwise gives `key`
+    /// back.
+    pub(crate) fn insert_if<F: FnOnce(Option<&Key>) -> bool>(&mut self, key: Key, should_write: F) -> Result<Option<Key>, Key> {
+        self.write_if(key, None, None, should_write)
     }
 
-    fn seek_to_last(&mut self) {
-        unimplemented!() // Requires full scan or back pointers
+    /// Inserts `key` in a node of the given height, clamped to between 1 and MAX_HEIGHT, rather
+    /// than a random one. Lets tests and deterministic builds lay out exact towers. Returns the
+    /// equal key it replaced if there was one, in which case the existing node keeps its height.
+    pub fn insert_with_height(&mut self, key: Key, height: usize) -> Option<Key> {
+        self.write_if(key, None, Some(height.clamp(1, MAX_HEIGHT)), |_| true).unwrap_or_else(|_| unreachable!())
     }
 
-    type Item = Key;
-}
+    /// Inserts `key` in a node at least `priority + 1` levels tall, clamped to MAX_HEIGHT, so keys
+    /// known to be hot are found after fewer steps. The height is otherwise random as for
+    /// `insert`, which priority 0 is the same as. Returns the equal key it replaced if there was
+    /// one, in which case the existing node keeps its height.
+    pub fn insert_with_priority(&mut self, key: Key, priority: usize) -> Option<Key> {
+        let height = self.random_height().max(priority.saturating_add(1)).min(MAX_HEIGHT);
+        self.write_if(key, None, Some(height), |_| true).unwrap_or_else(|_| unreachable!())
+    }
 
-struct Node<Key: Ord> {
-    key: Key,
-    links: Vec<Link<Key>>,
-}
+    // the write path shared by every insert. An expired equal key is overwritten as if absent. A
+    // new node is given `height` if set, otherwise a random height.
+    fn write_if<F: FnOnce(Option<&Key>) -> bool>(&mut self, key: Key, expires_at: Option<u64>, height: Option<usize>, should_write: F) -> Result<Option<Key>, Key> {
+        // A key greater than the cached last node goes straight after the tails, which are taken
+        // while the node is linked.
+        let hinted = self.append_hint
+            && self.tails.as_ref().is_some_and(|tails| self.is_head(tails.0[0]) || unsafe { (*tails.0[0].as_ptr()).key < key });
+        let appended = if hinted { self.tails.take() } else { None };
+        let levels = self.height();
+        let mut searched = None;
+        let (node, (previous, ranks)) = match &appended {
+            Some(tails) => (None, &**tails),
+            None => {
+                let (node, path) = self.find_equal_or_less_then(&key);
+                (node, &**searched.insert(path))
+            }
+        };
+        // Set to the new node if it lands at the end of the list.
+        let mut last = None;
+        let written = unsafe {
+            // 1.
+            match node {
+                Some(node) => {
+                    let live = !self.is_expired(node);
+                    if should_write(live.then(|| &(*node.as_ptr()).key)) {
+                        (*node.as_ptr()).expires_at = expires_at;
+                        let replaced = std::mem::replace(&mut (*node.as_ptr()).key, key);
+                        Ok(live.then_some(replaced))
+                    } else {
+                        Err(key)
+                    }
+                }
+                None if !should_write(None) => Err(key),
+                None => {
+                    let height = height.unwrap_or_else(|| self.random_height());
+                    let node = self.insert_after(key, height, previous, ranks);
+                    (*node.as_ptr()).expires_at = expires_at;
+                    if self.append_hint && (*node.as_ptr()).next(0).is_none() {
+                        last = Some(node);
+                    }
+                    Ok(None)
+                }
+            }
+        };
+        // Taken tails go back whether or not a node was linked after them. A searched insert which
+        // lands at the end starts a new run of appends from a copy of its path.
+        let tails = match (appended, last) {
+            (Some(tails), _) => Some(tails),
+            (None, Some(_)) => searched.as_deref().map(|path| Box::new(*path)),
+            (None, None) => None,
+        };
+        if let Some(path) = searched {
+            self.put_path(path, levels);
+        }
+        if let Some(mut tails) = tails {
+            if let Some(node) = last {
+                unsafe { Self::extend_tails(node, &mut tails) };
+            }
+            self.tails = Some(tails);
+        }
+        written
+    }
 
-type Link<Key> = Option<NonNull<Node<Key>>>;
+    /// Appends `key` after every key in the list without searching for its position, for loading
+    /// input which is already sorted such as the replay of a sorted log. Consecutive appends reuse
+    /// the tail of each level cached by the previous one.
+    ///
+    /// # Safety
+    /// `key` must be strictly greater than every key in the list. Otherwise the list is no longer
+    /// sorted and searches, iterators and removals over it have undefined behavior. Debug builds
+    /// assert the contract.
+    pub unsafe fn insert_ascending_unchecked(&mut self, key: Key) {
+        let mut tails = match self.tails.take() {
+            Some(tails) => tails,
+            None => Box::new(self.find_previous_by(|_| true)),
+        };
+        debug_assert!(self.is_head(tails.0[0]) || (*tails.0[0].as_ptr()).key < key, "keys must be strictly ascending");
+        let height = self.random_height();
+        let node = self.insert_after(key, height, &tails.0, &tails.1);
+        Self::extend_tails(node, &mut tails);
+        self.tails = Some(tails);
+    }
 
-impl<'a, Key: Ord + Default> Node<Key> {
-    fn new(key: Key, height: usize) -> Self {
-        Self {
-            key,
-            links: vec![None; height],
+    // moves `tails` past `node` once it is appended to the end of the list after them.
+    unsafe fn extend_tails(node: NonNull<Node<Key>>, tails: &mut Path<Key, MAX_HEIGHT>) {
+        let (tails, ranks) = tails;
+        let rank = ranks[0] + 1;
+        for level in 0..(*node.as_ptr()).height() {
+            tails[level] = node;
+            ranks[level] = rank;
         }
     }
 
-    fn new_link(key: Key, height: usize) -> NonNull<Node<Key>> {
+    /// Moves every key greater than or equal to `key` into a new list, leaving the smaller keys in
+    /// this one. The links are cut at `key` on each level so no nodes are visited besides those on
+    /// the search path. The new list shares the allocator but not the prefix extractor, metrics
+    /// sink or clock, which are left at their defaults.
+    pub fn split_off(&mut self, key: &Key) -> Self
+    where
+        A: Clone,
+    {
+        let (previous, ranks) = self.find_previous_by(|k| k < key);
+        let mut other = Self::new_in(self.alloc.clone());
+        let split_rank = ranks[0];
         unsafe {
-            NonNull::new_unchecked(Box::into_raw(Box::new(Node {
-                key,
-                links: vec![None; height],
-            })))
+            for (i, previous_node) in previous.iter().enumerate().take(self.height()) {
+                let previous_node = previous_node.as_ptr();
+                if let Some(next_node) = (*previous_node).next(i) {
+                    (*other.head.as_ptr()).set_next(i, Some(next_node));
+                    (*other.head.as_ptr()).set_span(i, ranks[i] + (*previous_node).span(i) - split_rank);
+                    (*previous_node).set_next(i, None);
+                    *other.current_height.get_mut() = i + 1;
+                }
+            }
+            self.shrink_height();
         }
+        *other.current_size.get_mut() = self.len() - split_rank;
+        *self.current_size.get_mut() = split_rank;
+        self.tails = None;
+        self.bump_generation();
+        other
     }
 
-    fn new_head(height: usize) -> NonNull<Node<Key>> {
+    /// Moves every key of `other` to the end of this list. Every key in `other` must be greater
+    /// than every key in this list, which debug builds assert. The towers at the end of this list
+    /// are linked to the towers at the front of `other` without searching `other`.
+    pub fn append(&mut self, mut other: Self) {
+        debug_assert!(
+            self.is_empty() || other.is_empty() || self.max() < other.min(),
+            "appended keys must be greater than every key in the list"
+        );
+        let (tails, ranks) = self.find_previous_by(|_| true);
+        self.grow_head(other.height());
         unsafe {
-            NonNull::new_unchecked(Box::into_raw(Box::new(Node {
-                key: Key::default(),
-                links: vec![None; height],
-            })))
+            for (i, tail) in tails.iter().enumerate().take(other.height()) {
+                let next_node = other.head_next(i);
+                (*tail.as_ptr()).set_next(i, next_node);
+                (*tail.as_ptr()).set_span(i, self.len() - ranks[i] + (*other.head.as_ptr()).span(i));
+                (*other.head.as_ptr()).set_next(i, None);
+            }
         }
+        let height = self.current_height.get_mut();
+        *height = (*height).max(other.height());
+        *self.current_size.get_mut() += other.len();
+        self.tails = None;
+        self.bump_generation();
+        self.alloc.absorb(&mut other.alloc);
+        // Only the head of `other` is left for it to free.
+        *other.current_height.get_mut() = 0;
+        *other.current_size.get_mut() = 0;
     }
 
-    #[inline(always)]
-    fn set_next(&mut self, n: usize, x: Link<Key>) {
-        self.links[n] = x;
+    /// Gives every node a fresh random height and relinks the towers in one pass over the keys,
+    /// for recovering a list left degenerate, such as nearly every node one level tall, by the
+    /// order it was built in. The keys stay in their nodes, only the towers are rebuilt.
+    pub fn rebuild(&mut self) {
+        let mut tails: [NonNull<Node<Key>>; MAX_HEIGHT] = std::array::from_fn(|_| self.head);
+        let mut ranks = [0; MAX_HEIGHT];
+        let mut height = 0;
+        unsafe {
+            let mut current = self.head_next(0);
+            self.grow_head(MAX_HEIGHT);
+            let head = self.head.as_ptr();
+            (*head).links.fill(None);
+            (*head).spans.fill(0);
+            let mut rank = 0;
+            while let Some(node) = current {
+                current = (*node.as_ptr()).next(0);
+                rank += 1;
+                let node_height = self.random_height();
+                let node_ref = &mut *node.as_ptr();
+                node_ref.links.clear();
+                node_ref.links.resize(node_height, None);
+                node_ref.spans.clear();
+                node_ref.spans.resize(node_height, 0);
+                for level in 0..node_height {
+                    (*tails[level].as_ptr()).set_next(level, Some(node));
+                    (*tails[level].as_ptr()).set_span(level, rank - ranks[level]);
+                    tails[level] = node;
+                    ranks[level] = rank;
+                }
+                height = height.max(node_height);
+            }
+        }
+        *self.current_height.get_mut() = height;
+        self.shrink_head();
+        self.tails = None;
+        self.bump_generation();
     }
 
-    #[inline(always)]
-    fn next(&self, n: usize) -> Link<Key> {
-        debug_assert!(n < self.links.len());
-        self.links[n]
+    /// Returns the key in the list equal to `key`, unless it has expired. The lookup takes any
+    /// borrowed form of the key, so a list of boxed keys such as `Box<[u8]>` can be searched with
+    /// a plain `&[u8]` without allocating.
+    pub fn get<Q>(&self, key: &Q) -> Option<&Key>
+    where
+        Key: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let node = self.find_first_not_before(|k| k.borrow() < key)?;
+        unsafe {
+            let found = &(*node.as_ptr()).key;
+            (found.borrow() == key && !self.is_expired(node)).then_some(found)
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Returns the key equal to `key` if it is in the list and has not expired. An expired key
+    /// found along the way is unlinked and freed.
+    pub fn access(&mut self, key: &Key) -> Option<&Key> {
+        let levels = self.height();
+        let mut path = self.take_path();
+        self.fill_previous_by(|k| k < key, &mut path);
+        let (previous, ranks) = &*path;
+        let found = unsafe {
+            (*previous[0].as_ptr()).next(0).filter(|node| (*node.as_ptr()).key == *key).and_then(|node| {
+                if self.is_expired(node) {
+                    self.unlink(node, previous);
+                    drop(self.release(node));
+                    return None;
+                }
+                if self.promotion == PromotionStrategy::AccessAdaptive && self.rng.u8(..4) == 0 {
+                    self.promote(node, previous, ranks);
+                }
+                Some(node)
+            })
+        };
+        self.put_path(path, levels);
+        found.map(|node| unsafe { &(*node.as_ptr()).key })
+    }
 
-    #[test]
-    fn test_seq_insert() {
-        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
-        for _i in 0..1000 {
-            list.insert(_i);
-            assert!(list.contains(&_i));
+    // raises `node` by one level, linking it after the node before it on that level. `previous`
+    // and `ranks` must be the nodes before `node` on each level and their ranks. Nodes already as
+    // tall as the list is worth are left alone.
+    unsafe fn promote(&mut self, node: NonNull<Node<Key>>, previous: &[NonNull<Node<Key>>; MAX_HEIGHT], ranks: &[usize; MAX_HEIGHT]) {
+        self.bump_generation();
+        let level = (*node.as_ptr()).height();
+        if level >= Self::height_cap(self.len()) {
+            return;
+        }
+        self.grow_head(level + 1);
+        let node = node.as_ptr();
+        let previous_node = previous[level].as_ptr();
+        let rank = ranks[0] + 1;
+        let next_node = (*previous_node).next(level);
+        (*node).links.push(next_node);
+        (*node).spans.push(0);
+        if next_node.is_some() {
+            (*node).set_span(level, ranks[level] + (*previous_node).span(level) - rank);
         }
+        (*previous_node).set_next(level, Some(NonNull::new_unchecked(node)));
+        (*previous_node).set_span(level, rank - ranks[level]);
+        let height = self.current_height.get_mut();
+        *height = (*height).max(level + 1);
+        self.tails = None;
     }
 
-    #[test]
-    fn test_insert_random_insert() {
-        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
-        for _i in 0..1000 {
-            let val = fastrand::i32(0..1000);
-            list.insert(val);
-            assert!(list.contains(&val));
+    // returns true if `node` was inserted with a ttl which has passed.
+    unsafe fn is_expired(&self, node: NonNull<Node<Key>>) -> bool {
+        (*node.as_ptr()).expires_at.is_some_and(|expires_at| expires_at <= self.clock.now())
+    }
+
+    // links a new node of `height` holding `key` after the nodes in `previous`, whose ranks are in
+    // `ranks`. A panic part way through unlinks and frees the node, leaving the list as it was.
+    unsafe fn insert_after(&mut self, key: Key, height: usize, previous: &[NonNull<Node<Key>>; MAX_HEIGHT], ranks: &[usize; MAX_HEIGHT]) -> NonNull<Node<Key>> {
+        self.tails = None;
+        self.bump_generation();
+        self.grow_head(height);
+        let node = self.allocate(key, height);
+        let splice = Splice::new(node, previous, self.height().max(height), &self.alloc);
+        let rank = ranks[0] + 1;
+        for (i, previous_node) in previous.iter().enumerate().take(height) {
+            let previous_node = previous_node.as_ptr();
+            let next_node = (*previous_node).next(i);
+            if let Some(next_node) = next_node {
+                debug_assert!((*next_node.as_ptr()).key > (*node.as_ptr()).key, "level {} would be out of order", i);
+                (*node.as_ptr()).set_span(i, ranks[i] + (*previous_node).span(i) + 1 - rank);
+            }
+            (*node.as_ptr()).set_next(i, next_node);
+            (*previous_node).set_next(i, Some(node));
+            (*previous_node).set_span(i, rank - ranks[i]);
+        }
+        // Links which pass over the new node now span one more position.
+        for (i, previous_node) in previous.iter().enumerate().take(self.height()).skip(height) {
+            let previous_node = previous_node.as_ptr();
+            if (*previous_node).next(i).is_some() {
+                (*previous_node).set_span(i, (*previous_node).span(i) + 1);
+            }
         }
+        std::mem::forget(splice);
+        let list_height = self.current_height.get_mut();
+        *list_height = (*list_height).max(height);
+        *self.current_size.get_mut() += 1;
+        node
+    }
+
+    // marks every cursor over the list as possibly pointing at a moved or freed node.
+    fn bump_generation(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    // returns a node of `height` holding `key`, reusing a freed node of that height if there is one.
+    fn allocate(&mut self, key: Key, height: usize) -> NonNull<Node<Key>> {
+        let Some(node) = self.free_nodes.get_mut(height - 1).and_then(Vec::pop) else {
+            return Node::new_link(key, height, &self.alloc);
+        };
+        unsafe {
+            let node_ref = &mut *node.as_ptr();
+            std::ptr::write(&mut node_ref.key, key);
+            node_ref.links.fill(None);
+            node_ref.spans.fill(0);
+            node_ref.expires_at = None;
+        }
+        node
+    }
+
+    // moves the key out of an unlinked node, keeping the node for a later insert of the same
+    // height unless enough of that height are already kept.
+    unsafe fn release(&mut self, node: NonNull<Node<Key>>) -> Key {
+        let height = (*node.as_ptr()).height();
+        if self.free_nodes.len() < height {
+            self.free_nodes.resize_with(height, Vec::new);
+        }
+        if self.free_nodes[height - 1].len() >= FREE_NODES_PER_HEIGHT {
+            return Node::into_key(node, &self.alloc);
+        }
+        self.free_nodes[height - 1].push(node);
+        std::ptr::read(&(*node.as_ptr()).key)
+    }
+
+    /// Removes and returns the first key for which `is_before` is false if `is_match` accepts it.
+    pub(crate) fn take_by<B, M>(&mut self, is_before: B, is_match: M) -> Option<Key>
+    where
+        B: Fn(&Key) -> bool,
+        M: FnOnce(&Key) -> bool,
+    {
+        let levels = self.height();
+        let mut path = self.take_path();
+        self.fill_previous_by(is_before, &mut path);
+        let previous = &path.0;
+        let taken = unsafe {
+            (*previous[0].as_ptr()).next(0).filter(|node| is_match(&(*node.as_ptr()).key)).map(|node| {
+                self.unlink(node, previous);
+                self.release(node)
+            })
+        };
+        self.put_path(path, levels);
+        taken
+    }
+
+    // find the node that is closest in value but less then. Alongside the previous node at each
+    // level the rank (level 0 position, head is 0) of that previous node is returned.
+    // The path is taken from the one reused by writes, which must be put back once done with.
+    fn find_equal_or_less_then(&mut self, key: &Key) -> (Link<Key>, Box<Path<Key, MAX_HEIGHT>>) {
+        let mut path = self.take_path();
+        let found = self.search(key, Some(&mut path));
+        (found, path)
+    }
+
+    // takes the search path reused by writes, every level of which holds the head at rank 0.
+    fn take_path(&mut self) -> Box<Path<Key, MAX_HEIGHT>> {
+        self.path.take().unwrap_or_else(|| Box::new((std::array::from_fn(|_| self.head), [0; MAX_HEIGHT])))
+    }
+
+    // puts back a path taken by `take_path`, resetting the lowest `levels` levels, which must be
+    // every level written since it was taken. A search only writes the levels in use when it began.
+    fn put_path(&mut self, mut path: Box<Path<Key, MAX_HEIGHT>>, levels: usize) {
+        path.0[..levels].fill(self.head);
+        path.1[..levels].fill(0);
+        self.path = Some(path);
+    }
+
+    // finds the node equal to `key` without recording the search path, so a lookup doesn't write
+    // a path at all.
+    fn find_equal(&self, key: &Key) -> Link<Key> {
+        self.search(key, None)
+    }
+
+    // the search shared by `find_equal_or_less_then` and `find_equal`. Writes the previous node
+    // and its rank on each level searched to `path` when given one.
+    fn search(&self, key: &Key, mut path: Option<&mut Path<Key, MAX_HEIGHT>>) -> Link<Key> {
+        let mut comparisons = 0;
+        let mut level_descents = 0;
+        unsafe {
+            // 1. Case where node is the smallest or other nodes exist in the tree
+            let first_node = self.head_next(0)?;
+            comparisons += 1;
+            if (*first_node.as_ptr()).key > *key {
+                self.record_search(comparisons, level_descents);
+                return None;
+            }
+            // 2. Search the rest of the list. Any level, even the head's, may have no link forward in
+            // which case the search drops down a level.
+            let mut search_level = self.height() - 1;
+            let mut current_node = self.head;
+            let mut rank = 0;
+            let mut last_greater: Link<Key> = None;
+            let found = loop {
+                if let Some((previous, ranks)) = path.as_deref_mut() {
+                    previous[search_level] = current_node;
+                    ranks[search_level] = rank;
+                }
+                match (*current_node.as_ptr()).next(search_level) {
+                    None => {
+                        if search_level == 0 {
+                            break None;
+                        }
+                        search_level -= 1;
+                        level_descents += 1;
+                    }
+                    Some(next_node) if self.cache_comparisons && last_greater == Some(next_node) => {
+                        // Already found to be greater than `key` on the level above.
+                        if search_level == 0 {
+                            break None;
+                        }
+                        search_level -= 1;
+                        level_descents += 1;
+                    }
+                    Some(next_node) => {
+                        comparisons += 1;
+                        if (*next_node.as_ptr()).key >= *key {
+                            comparisons += 1;
+                            if (*next_node.as_ptr()).key == *key {
+                                break Some(next_node);
+                            }
+                            Self::debug_assert_total_order(&(*next_node.as_ptr()).key, key);
+                            if search_level == 0 {
+                                break None;
+                            }
+                            last_greater = Some(next_node);
+                            search_level -= 1;
+                            level_descents += 1;
+                        } else {
+                            rank += (*current_node.as_ptr()).span(search_level);
+                            current_node = next_node;
+                        }
+                    }
+                };
+            };
+            self.record_search(comparisons, level_descents);
+            found
+        }
+    }
+
+    // find the node that is equal or closest greatest value, and its rank. Useful for iteration.
+    fn find_equal_or_greater_then(&self, key: &Key) -> Option<(NonNull<Node<Key>>, usize)> {
+        let mut comparisons = 0;
+        let mut level_descents = 0;
+        unsafe {
+            // 1. Case where node is the smallest or other nodes exist in the tree
+            let first_node = self.head_next(0)?;
+            comparisons += 1;
+            if (*first_node.as_ptr()).key > *key {
+                self.record_search(comparisons, level_descents);
+                return Some((first_node, 1));
+            }
+            // 2. Search the rest of the list.
+            let mut search_level = self.height() - 1;
+            let mut current_node = self.head;
+            let mut rank = 0;
+            let mut last_greater: Link<Key> = None;
+            let found = loop {
+                match (*current_node.as_ptr()).next(search_level) {
+                    None => {
+                        if search_level == 0 {
+                            break None;
+                        }
+                        search_level -= 1;
+                        level_descents += 1;
+                    }
+                    Some(next_node) if self.cache_comparisons && last_greater == Some(next_node) => {
+                        // Already found to be greater than `key` on the level above.
+                        if search_level == 0 {
+                            break Some((next_node, rank + (*current_node.as_ptr()).span(search_level)));
+                        }
+                        search_level -= 1;
+                        level_descents += 1;
+                    }
+                    Some(next_node) => {
+                        comparisons += 1;
+                        if (*next_node.as_ptr()).key >= *key {
+                            comparisons += 1;
+                            if (*next_node.as_ptr()).key == *key {
+                                break Some((next_node, rank + (*current_node.as_ptr()).span(search_level)));
+                            }
+                            Self::debug_assert_total_order(&(*next_node.as_ptr()).key, key);
+                            if search_level == 0 {
+                                break Some((next_node, rank + (*current_node.as_ptr()).span(search_level)));
+                            }
+                            last_greater = Some(next_node);
+                            search_level -= 1;
+                            level_descents += 1;
+                        } else {
+                            rank += (*current_node.as_ptr()).span(search_level);
+                            current_node = next_node;
+                        }
+                    }
+                };
+            };
+            self.record_search(comparisons, level_descents);
+            found
+        }
+    }
+
+    // checks keys which are not == do not compare Equal either. A search stops at the first key
+    // == to its target, so an Ord which disagrees would let a second equal key be inserted.
+    #[inline(always)]
+    fn debug_assert_total_order(a: &Key, b: &Key) {
+        debug_assert!(a.cmp(b) != Ordering::Equal, "keys compare Equal but are not ==, the key's Ord and PartialEq disagree");
+    }
+
+    // reports the cost of a search to the metrics sink, if one is set.
+    #[inline(always)]
+    fn record_search(&self, comparisons: usize, level_descents: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_search(comparisons, level_descents);
+        }
+    }
+
+    // find the last node for which `is_before` returns true, or the head if there is none.
+    // `is_before` must be true for a (possibly empty) run of the smallest keys and false for every
+    // key after it.
+    fn find_last_before<F: Fn(&Key) -> bool>(&self, is_before: F) -> NonNull<Node<Key>> {
+        self.find_last_before_ranked(is_before).0
+    }
+
+    // as `find_last_before`, also returning the rank of the node found.
+    fn find_last_before_ranked<F: Fn(&Key) -> bool>(&self, is_before: F) -> (NonNull<Node<Key>>, usize) {
+        unsafe {
+            let mut current_node = self.head;
+            let mut rank = 0;
+            for search_level in (0..self.height()).rev() {
+                while let Some(next_node) = (*current_node.as_ptr()).next(search_level) {
+                    if !is_before(&(*next_node.as_ptr()).key) {
+                        break;
+                    }
+                    rank += (*current_node.as_ptr()).span(search_level);
+                    current_node = next_node;
+                }
+            }
+            (current_node, rank)
+        }
+    }
+
+    // find the last node on every level for which `is_before` returns true (or the head), which
+    // are the nodes whose links must change to insert or remove the node after them, along with
+    // the rank of each.
+    fn find_previous_by<F: Fn(&Key) -> bool>(&mut self, is_before: F) -> Path<Key, MAX_HEIGHT> {
+        let mut path = (std::array::from_fn(|_| self.head), [0; MAX_HEIGHT]);
+        self.fill_previous_by(is_before, &mut path);
+        path
+    }
+
+    // writes the nodes and ranks found by `find_previous_by` to the levels in use of `path`,
+    // leaving the levels above as they are.
+    fn fill_previous_by<F: Fn(&Key) -> bool>(&mut self, is_before: F, path: &mut Path<Key, MAX_HEIGHT>) {
+        let (previous, ranks) = path;
+        unsafe {
+            let mut current_node = self.head;
+            let mut rank = 0;
+            for search_level in (0..self.height()).rev() {
+                while let Some(next_node) = (*current_node.as_ptr()).next(search_level) {
+                    if !is_before(&(*next_node.as_ptr()).key) {
+                        break;
+                    }
+                    rank += (*current_node.as_ptr()).span(search_level);
+                    current_node = next_node;
+                    // The search would have stepped onto a node this tall on the level above.
+                    if self.read_repair && (*current_node.as_ptr()).height() > search_level + 1 {
+                        self.relink(current_node, rank, search_level + 1, previous, ranks);
+                    }
+                }
+                previous[search_level] = current_node;
+                ranks[search_level] = rank;
+            }
+        }
+    }
+
+    // links `node`, at `rank`, back in on each level from `from_level` up to its height. It must
+    // be missing from those levels and lie between the nodes `previous` holds for them and their
+    // next nodes, which the search that found it guarantees. `node` replaces them in `previous`.
+    unsafe fn relink(&mut self, node: NonNull<Node<Key>>, rank: usize, from_level: usize, previous: &mut [NonNull<Node<Key>>; MAX_HEIGHT], ranks: &mut [usize; MAX_HEIGHT]) {
+        self.bump_generation();
+        for level in from_level..(*node.as_ptr()).height().min(self.height()) {
+            let previous_node = previous[level].as_ptr();
+            let next_node = (*previous_node).next(level);
+            let span = if next_node.is_some() { ranks[level] + (*previous_node).span(level) - rank } else { 0 };
+            (*node.as_ptr()).set_next(level, next_node);
+            (*node.as_ptr()).set_span(level, span);
+            (*previous_node).set_next(level, Some(node));
+            (*previous_node).set_span(level, rank - ranks[level]);
+            previous[level] = node;
+            ranks[level] = rank;
+            if let Some(metrics) = &self.metrics {
+                metrics.record_repair(level);
+            }
+        }
+        self.tails = None;
+    }
+
+    // unlink `node` from every level. `previous` must hold the node before `node` on each level.
+    unsafe fn unlink(&mut self, node: NonNull<Node<Key>>, previous: &[NonNull<Node<Key>>; MAX_HEIGHT]) {
+        self.tails = None;
+        self.bump_generation();
+        for (i, previous_node) in previous.iter().enumerate().take(self.height()) {
+            let previous_node = previous_node.as_ptr();
+            match (*previous_node).next(i) {
+                Some(next_node) if next_node == node => {
+                    let next_node = (*node.as_ptr()).next(i);
+                    if next_node.is_some() {
+                        (*previous_node).set_span(i, (*previous_node).span(i) + (*node.as_ptr()).span(i) - 1);
+                    }
+                    (*previous_node).set_next(i, next_node);
+                }
+                Some(_) => (*previous_node).set_span(i, (*previous_node).span(i) - 1),
+                None => (),
+            }
+        }
+        self.shrink_height();
+        *self.current_size.get_mut() -= 1;
+    }
+
+    // find the first node for which `is_before` returns false.
+    fn find_first_not_before<F: Fn(&Key) -> bool>(&self, is_before: F) -> Link<Key> {
+        unsafe { (*self.find_last_before(is_before).as_ptr()).next(0) }
+    }
+
+    // find the last node before `key` on each level, resuming from the nodes in `previous` which
+    // must all have keys less than `key` (or be the head). Lets a sweep over ascending keys reuse
+    // the progress of the prior search rather than starting from the head each time.
+    fn find_less_then_from(&self, key: &Key, previous: &mut [NonNull<Node<Key>>; MAX_HEIGHT]) {
+        unsafe {
+            let mut current_node = self.head;
+            for search_level in (0..self.height()).rev() {
+                let resume_node = previous[search_level];
+                if self.is_head(current_node)
+                    || (!self.is_head(resume_node) && (*resume_node.as_ptr()).key > (*current_node.as_ptr()).key) {
+                    current_node = resume_node;
+                }
+                while let Some(next_node) = (*current_node.as_ptr()).next(search_level) {
+                    if (*next_node.as_ptr()).key >= *key {
+                        break;
+                    }
+                    current_node = next_node;
+                }
+                previous[search_level] = current_node;
+            }
+        }
+    }
+
+    // sweeps `keys` in order calling `found` with whether each is in the list, stopping early once
+    // `found` returns false. Ascending input is searched with a single sweep.
+    fn sweep_contains<F: FnMut(bool) -> bool>(&self, keys: &[Key], mut found: F) {
+        let mut previous: [NonNull<Node<Key>>; MAX_HEIGHT] = std::array::from_fn(|_| self.head);
+        let mut last_key: Option<&Key> = None;
+        for key in keys {
+            if last_key.is_some_and(|last_key| last_key > key) {
+                previous = std::array::from_fn(|_| self.head); // Out of order, restart from the head.
+            }
+            last_key = Some(key);
+            self.find_less_then_from(key, &mut previous);
+            let next_node = unsafe { (*previous[0].as_ptr()).next(0) };
+            let contained = next_node.is_some_and(|node| unsafe { (*node.as_ptr()).key == *key });
+            if !found(contained) {
+                return;
+            }
+        }
+    }
+
+    /// Removes every key in `keys` which is in the list, returning how many were removed. Sorted
+    /// input is removed in a single sweep rather than searching from the head for each key.
+    pub fn remove_sorted<I: IntoIterator<Item = Key>>(&mut self, keys: I) -> usize {
+        let mut previous: [NonNull<Node<Key>>; MAX_HEIGHT] = std::array::from_fn(|_| self.head);
+        let mut last_key: Option<Key> = None;
+        let mut removed = 0;
+        for key in keys {
+            if last_key.as_ref().is_some_and(|last_key| *last_key > key) {
+                previous = std::array::from_fn(|_| self.head); // Out of order, restart from the head.
+            }
+            self.find_less_then_from(&key, &mut previous);
+            unsafe {
+                let next_node = (*previous[0].as_ptr()).next(0);
+                if let Some(node) = next_node.filter(|node| (*node.as_ptr()).key == key) {
+                    self.unlink(node, &previous);
+                    drop(self.release(node));
+                    removed += 1;
+                }
+            }
+            last_key = Some(key);
+        }
+        removed
+    }
+
+    /// Returns true if every key in `keys` is in the list. Sorted input is checked in a single
+    /// sweep, stopping at the first missing key.
+    pub fn contains_all(&self, keys: &[Key]) -> bool {
+        let mut all = true;
+        self.sweep_contains(keys, |contained| {
+            all = contained;
+            contained
+        });
+        all
+    }
+
+    /// Returns true if any key in `keys` is in the list. Sorted input is checked in a single
+    /// sweep, stopping at the first key found.
+    pub fn contains_any(&self, keys: &[Key]) -> bool {
+        let mut any = false;
+        self.sweep_contains(keys, |contained| {
+            any = contained;
+            !contained
+        });
+        any
+    }
+
+    #[inline(always)]
+    pub fn get_max_height(&self) -> usize {
+        self.height()
+    }
+
+    // the number of levels in use.
+    #[inline(always)]
+    fn height(&self) -> usize {
+        self.current_height.load(AtomicOrdering::Relaxed)
+    }
+
+    // drops the empty levels from the top of the list, and the head's links for them.
+    fn shrink_height(&mut self) {
+        let height = self.current_height.get_mut();
+        while *height > 0 && unsafe { (*self.head.as_ptr()).next(*height - 1) }.is_none() {
+            *height -= 1;
+        }
+        self.shrink_head();
+    }
+
+    // reallocates the head's links to only the levels in use, so a list which has lost its tall
+    // towers does not keep MAX_HEIGHT links. They grow back through `grow_head`. The bottom level
+    // is kept even when empty, searches read it from the head without checking the height.
+    fn shrink_head(&mut self) {
+        let height = self.height().max(1);
+        let head = unsafe { &mut *self.head.as_ptr() };
+        if head.height() > height {
+            head.links.truncate(height);
+            head.links.shrink_to_fit();
+            head.spans.truncate(height);
+            head.spans.shrink_to_fit();
+        }
+    }
+
+    // gives the head links up to `height`, before a tower of that height is linked after it.
+    fn grow_head(&mut self, height: usize) {
+        let head = unsafe { &mut *self.head.as_ptr() };
+        if head.height() < height {
+            head.links.resize(height, None);
+            head.spans.resize(height, 0);
+        }
+    }
+
+    // picks the height of a new node, capped by `height_cap` for the size the list will have.
+    #[inline(always)]
+    fn random_height(&mut self) -> usize {
+        let cap = Self::height_cap(self.len() + 1);
+        let mut height = 1;
+        while height < cap && self.rng.bool() {
+            height += 1
+        }
+        height
+    }
+
+    // the tallest tower worth building in a list of `size` keys. Towers much taller than
+    // log2(size) are almost never traversed, so the cap grows with the list up to MAX_HEIGHT.
+    fn height_cap(size: usize) -> usize {
+        const MARGIN: usize = 2;
+        let log2_ceil = (usize::BITS - size.saturating_sub(1).leading_zeros()) as usize;
+        (log2_ceil + MARGIN).clamp(1, MAX_HEIGHT)
+    }
+
+    // returns true if `node` is the head. The head holds `Key::default()` only to fill its key,
+    // which may sort anywhere among the real keys, so it is told apart by identity and its key is
+    // never compared.
+    #[inline(always)]
+    fn is_head(&self, node: NonNull<Node<Key>>) -> bool {
+        node == self.head
+    }
+
+    // The head may have fewer links than MAX_HEIGHT, levels above them are empty.
+    #[inline(always)]
+    unsafe fn head_next(&self, level: usize) -> Link<Key> {
+        (*self.head.as_ptr()).links.as_slice().get(level).copied().flatten()
+    }
+}
+
+impl<'a, Key: Ord + Default + Display, const MAX_HEIGHT: usize, A: NodeAllocator> LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {
+    /// Returns every level of the list, top level first, one line per level.
+    pub fn dump(&self) -> String {
+        let mut dump = String::new();
+        for i in (0..self.height()).rev() {
+            unsafe {
+                let mut next_node = (*self.head.as_ptr()).next(i);
+                loop {
+                    match next_node {
+                        Some(node) => {
+                            dump.push_str(&format!("-> [ {} ]", (*node.as_ptr()).key));
+                            next_node = (*node.as_ptr()).next(i);
+                        }
+                        None => {
+                            dump.push_str("-> None\n");
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        dump
+    }
+
+    /// Prints `dump` to stdout.
+    pub fn print(&self) {
+        print!("{}", self.dump());
+    }
+}
+
+impl<'a, Key: Ord + Default + AsRef<[u8]>, const MAX_HEIGHT: usize, A: NodeAllocator> LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {
+    /// Returns every key whose extracted prefix starts with `prefix`, in ascending order.
+    pub fn prefix_iter<'b>(&'b self, prefix: &'b [u8]) -> impl Iterator<Item = &'b Key> + 'b {
+        let extractor: &dyn PrefixExtractor<Key> = match &self.prefix_extractor {
+            Some(extractor) => extractor.as_ref(),
+            None => &IdentityExtractor,
+        };
+        let start = self.find_first_not_before(|key| extractor.prefix(key) < prefix);
+        unsafe { self.iter_at(start) }.take_while(move |key| extractor.prefix(key).starts_with(prefix))
+    }
+
+    /// Counts keys by their length in bytes. Bucket 0 counts empty keys and bucket `i` counts
+    /// keys of `2^(i-1)` up to `2^i - 1` bytes, the last bucket also counting every longer key.
+    pub fn key_size_histogram<const N_BUCKETS: usize>(&self) -> [usize; N_BUCKETS] {
+        let mut histogram = [0; N_BUCKETS];
+        if N_BUCKETS == 0 {
+            return histogram;
+        }
+        for key in self.iter() {
+            let bucket = (usize::BITS - key.as_ref().len().leading_zeros()) as usize;
+            histogram[bucket.min(N_BUCKETS - 1)] += 1;
+        }
+        histogram
+    }
+}
+
+impl<'a, Key: Ord + Default + Distance, const MAX_HEIGHT: usize, A: NodeAllocator> LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {
+    /// Returns the key nearest to `key` along with how it compares to `key`. An exact match is
+    /// returned as `Equal`, otherwise the nearer of the predecessor (`Less`) and successor
+    /// (`Greater`) is returned, preferring the predecessor on a tie.
+    pub fn closest(&self, key: &Key) -> Option<(&Key, Ordering)> {
+        unsafe {
+            let previous = self.find_last_before(|k| k < key);
+            let predecessor = (!self.is_head(previous)).then(|| &(*previous.as_ptr()).key);
+            let successor = (*previous.as_ptr()).next(0).map(|node| &(*node.as_ptr()).key);
+            match (predecessor, successor) {
+                (_, Some(successor)) if successor == key => Some((successor, Ordering::Equal)),
+                (Some(predecessor), Some(successor)) => {
+                    if key.distance(predecessor) <= key.distance(successor) {
+                        Some((predecessor, Ordering::Less))
+                    } else {
+                        Some((successor, Ordering::Greater))
+                    }
+                }
+                (Some(predecessor), None) => Some((predecessor, Ordering::Less)),
+                (None, Some(successor)) => Some((successor, Ordering::Greater)),
+                (None, None) => None,
+            }
+        }
+    }
+}
+
+impl<'a, Key: Ord + Default + Debug, const MAX_HEIGHT: usize, A: NodeAllocator> Debug for LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+// Safety: the list owns its nodes, so moving it to another thread moves the keys and the allocator
+// with it. The metrics sink, clock and prefix extractor are required to be Send.
+unsafe impl<'a, Key: Ord + Default + Send, const MAX_HEIGHT: usize, A: NodeAllocator + Send> Send for LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {}
+
+impl<'a, Key: Ord + Default, const MAX_HEIGHT: usize> Default for LinkedListSkipList<'a, Key, MAX_HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a list from the keys of a set, appending them in order without searching.
+impl<'a, Key: Ord + Default, const MAX_HEIGHT: usize> From<BTreeSet<Key>> for LinkedListSkipList<'a, Key, MAX_HEIGHT> {
+    fn from(set: BTreeSet<Key>) -> Self {
+        let mut list = Self::new();
+        for key in set {
+            // Safety: a set yields its keys strictly ascending.
+            unsafe { list.insert_ascending_unchecked(key) };
+        }
+        list
+    }
+}
+
+impl<'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> SkipList<Key> for LinkedListSkipList<'a, Key, MAX_HEIGHT, A>
+{
+    fn insert(&mut self, key: Key) {
+        self.replace(key);
+    }
+
+    fn contains(&self, key: &Key) -> bool {
+        let node = self.find_equal(key);
+        node.is_some_and(|node| unsafe { !self.is_expired(node) })
+    }
+
+    fn remove(&mut self, key: &Key) -> bool {
+        self.take_by(|k| k < key, |k| k == key).is_some()
+    }
+
+    fn min(&self) -> Option<&Key> {
+        unsafe { self.head_next(0).map(|node| &(*node.as_ptr()).key) }
+    }
+
+    fn max(&self) -> Option<&Key> {
+        let last = self.find_last_before(|_| true);
+        unsafe { (!self.is_head(last)).then(|| &(*last.as_ptr()).key) }
+    }
+
+    fn estimate_count(&self, _key: &Key) -> usize {
+        self.len()
+    }
+
+    fn balance_factor(&self) -> f64 {
+        if self.len() < 2 {
+            return 1.0;
+        }
+        let mut counts = vec![0; self.height() + 1];
+        unsafe {
+            for (level, count) in counts.iter_mut().enumerate().take(self.height()) {
+                let mut current = self.head_next(level);
+                while let Some(node) = current {
+                    *count += 1;
+                    current = (*node.as_ptr()).next(level);
+                }
+            }
+        }
+        // A search crosses about half the nodes on a level between each pair of nodes on the level
+        // above, so an ideal list with half the nodes on each level costs 2 * log2(size).
+        let cost: f64 = counts.windows(2).map(|pair| (pair[0] + 1) as f64 / (pair[1] + 1) as f64).sum();
+        cost / (2.0 * (self.len() as f64).log2())
+    }
+}
+
+impl<'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> Drop for LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {
+    fn drop(&mut self) {
+        unsafe {
+            // Start from the head of the list  // Iterate over each node and deallocate it
+            let mut current_node = self.head_next(0);
+            while let Some(node) = current_node {
+                current_node = (*node.as_ptr()).next(0);
+                Node::free(node, &self.alloc);
+            }
+            Node::free(self.head, &self.alloc); // deallocate the head node
+            for node in self.free_nodes.drain(..).flatten() {
+                Node::free_released(node, &self.alloc);
+            }
+        }
+    }
+}
+
+impl<'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> IntoIterator for LinkedListSkipList<'a, Key, MAX_HEIGHT, A>
+    where
+        Key: Ord + 'a,
+{
+    type Item = &'a Key;
+    type IntoIter = LinkedListSkipListIterator<'a, Key, MAX_HEIGHT, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        LinkedListSkipListIterator {
+            state: CursorState::new(Some(self.head), 0),
+            skip_list: self,
+        }
+    }
+}
+
+pub struct LinkedListSkipListIterator<'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator = Global>
+    where
+        Key: Ord,
+{
+    skip_list: LinkedListSkipList<'a, Key, MAX_HEIGHT, A>,
+    state: CursorState<Key>,
+}
+
+
+impl<'a, Key: Ord + Default + 'a, const MAX_HEIGHT: usize, A: NodeAllocator> Iterator for LinkedListSkipListIterator<'a, Key, MAX_HEIGHT, A> {
+    type Item = &'a Key;
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe { self.state.next() }
+    }
+}
+
+impl<'a, Key: Ord + Default + 'a, const MAX_HEIGHT: usize, A: NodeAllocator> SkipListIterator<&'a Key> for LinkedListSkipListIterator<'a, Key, MAX_HEIGHT, A>
+{
+    fn valid(&self) -> bool {
+        self.state.valid()
+    }
+
+    fn key(&self) -> Option<&'a Key> {
+        unsafe { self.state.key(&self.skip_list) }
+    }
+
+    fn advance(&mut self) {
+        self.state.advance()
+    }
+
+    fn skip_forward(&mut self, n: usize) {
+        self.state.skip_forward(n)
+    }
+
+    fn supports_reverse(&self) -> bool {
+        true
+    }
+
+    fn prev(&mut self) -> Result<(), SkipListError> {
+        self.state.prev(&self.skip_list)
+    }
+
+    fn seek(&mut self, _target: &Key) -> Result<bool, SeekError> {
+        self.state.seek(&self.skip_list, _target)
+    }
+
+    fn seek_exact(&mut self, target: &Key) -> Result<bool, SeekError> {
+        self.state.seek_exact(&self.skip_list, target)
+    }
+
+    fn seek_for_prev(&mut self, _target: &Key) -> Result<(), SkipListError> {
+        self.state.seek_for_prev(&self.skip_list, _target)
+    }
+
+    fn seek_to_first(&mut self) {
+        self.state.seek_to_first(&self.skip_list)
+    }
+
+    fn reset(&mut self) {
+        self.state.reset(&self.skip_list)
+    }
+
+    fn seek_to_last(&mut self) -> Result<(), SkipListError> {
+        self.state.seek_to_last(&self.skip_list)
+    }
+
+    type Item = Key;
+}
+
+/// Cursor over a borrowed list, created by `LinkedListSkipList::cursor`. Cloning a cursor is
+/// cheap and gives an independent cursor at the same position.
+pub struct SkipListCursor<'c, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator = Global> {
+    list: &'c LinkedListSkipList<'a, Key, MAX_HEIGHT, A>,
+    state: CursorState<Key>,
+    // The list's generation when the cursor was last positioned.
+    generation: usize,
+}
+
+impl<'c, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> SkipListCursor<'c, 'a, Key, MAX_HEIGHT, A> {
+    /// Returns the zero based position of the current key among the keys of the list, kept up to
+    /// date as the cursor moves from the spans of the links it follows. Requires that the cursor
+    /// is at a key, which debug builds assert.
+    pub fn position(&self) -> usize {
+        self.assert_current();
+        debug_assert!(self.state.valid() && self.state.rank > 0, "position called on a cursor which is not at a key");
+        self.state.rank.wrapping_sub(1)
+    }
+
+    /// Advances up to `n` positions one at a time on the bottom level, returning the number of
+    /// keys moved onto. Returns less than `n` if the end of the list was reached, leaving the
+    /// cursor invalid. Unlike `skip_forward` each step can be undone by `prev`.
+    pub fn advance_by(&mut self, n: usize) -> usize {
+        self.assert_current();
+        let mut advanced = 0;
+        while advanced < n && self.state.valid() {
+            self.state.advance();
+            if self.state.valid() {
+                advanced += 1;
+            }
+        }
+        advanced
+    }
+
+    // Checks in debug builds that the list has not been modified since the cursor was positioned,
+    // which could have freed the node it is at. The borrow of the list rules this out for callers,
+    // so it only catches code in this module carrying a cursor's position across a write.
+    fn assert_current(&self) {
+        debug_assert_eq!(self.generation, self.list.generation, "cursor used after the list it was positioned in was modified");
+    }
+
+    // Marks the cursor as positioned in the list as it is now, after a seek moves it.
+    fn restamp(&mut self) {
+        self.generation = self.list.generation;
+    }
+}
+
+impl<'c, 'a, Key: Ord + Default + Clone + Send, const MAX_HEIGHT: usize, A: NodeAllocator> SkipListCursor<'c, 'a, Key, MAX_HEIGHT, A> {
+    /// Clones the keys from the cursor's position on into an iterator which owns them, so it can
+    /// be handed to another thread, such as a worker in a pool, while the list stays with its
+    /// owner. The keys are cloned up front, costing O(n) in the keys remaining.
+    pub fn into_owned_iter(self) -> impl Iterator<Item = Key> + Send + 'static
+    where
+        Key: 'static,
+    {
+        self.cloned().collect::<Vec<Key>>().into_iter()
+    }
+}
+
+impl<'c, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> Clone for SkipListCursor<'c, 'a, Key, MAX_HEIGHT, A> {
+    fn clone(&self) -> Self {
+        Self {
+            list: self.list,
+            state: self.state.clone(),
+            generation: self.generation,
+        }
+    }
+}
+
+impl<'c, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> Iterator for SkipListCursor<'c, 'a, Key, MAX_HEIGHT, A> {
+    type Item = &'c Key;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.assert_current();
+        unsafe { self.state.next() }
+    }
+}
+
+impl<'c, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> SkipListIterator<&'c Key> for SkipListCursor<'c, 'a, Key, MAX_HEIGHT, A>
+{
+    fn valid(&self) -> bool {
+        self.state.valid()
+    }
+
+    fn key(&self) -> Option<&'c Key> {
+        self.assert_current();
+        unsafe { self.state.key(self.list) }
+    }
+
+    fn advance(&mut self) {
+        self.assert_current();
+        self.state.advance()
+    }
+
+    fn skip_forward(&mut self, n: usize) {
+        self.assert_current();
+        self.state.skip_forward(n)
+    }
+
+    fn supports_reverse(&self) -> bool {
+        true
+    }
+
+    fn prev(&mut self) -> Result<(), SkipListError> {
+        self.assert_current();
+        self.state.prev(self.list)
+    }
+
+    fn seek(&mut self, target: &Key) -> Result<bool, SeekError> {
+        let found = self.state.seek(self.list, target)?;
+        self.restamp();
+        Ok(found)
+    }
+
+    fn seek_exact(&mut self, target: &Key) -> Result<bool, SeekError> {
+        let found = self.state.seek_exact(self.list, target)?;
+        if found {
+            self.restamp();
+        }
+        Ok(found)
+    }
+
+    fn seek_for_prev(&mut self, target: &Key) -> Result<(), SkipListError> {
+        self.state.seek_for_prev(self.list, target)?;
+        self.restamp();
+        Ok(())
+    }
+
+    fn seek_to_first(&mut self) {
+        self.state.seek_to_first(self.list);
+        self.restamp();
+    }
+
+    fn reset(&mut self) {
+        self.state.reset(self.list);
+        self.restamp();
+    }
+
+    fn seek_to_last(&mut self) -> Result<(), SkipListError> {
+        self.state.seek_to_last(self.list)?;
+        self.restamp();
+        Ok(())
+    }
+
+    type Item = Key;
+}
+
+// Position of a cursor within a list, shared by the owning and borrowing cursors. Keys handed out
+// are only valid while the list they came from is alive and unmodified.
+struct CursorState<Key: Ord> {
+    current: Link<Key>,
+    // Rank of `current`, the head being 0. Meaningless once the cursor is invalid.
+    rank: usize,
+    // Nodes stepped forward from, most recent last, for stepping back without a search.
+    history: History<Key>,
+}
+
+impl<Key: Ord> Clone for CursorState<Key> {
+    fn clone(&self) -> Self {
+        Self {
+            current: self.current,
+            rank: self.rank,
+            history: self.history.clone(),
+        }
+    }
+}
+
+// Number of steps forward a cursor remembers for `prev`.
+const HISTORY_LEN: usize = 16;
+
+// Ring buffer of the last `HISTORY_LEN` nodes a cursor stepped forward from, each one the
+// predecessor of the next. Pushing onto a full buffer forgets the oldest node.
+struct History<Key: Ord> {
+    nodes: [Link<Key>; HISTORY_LEN],
+    // Slot the next node is pushed to.
+    top: usize,
+    len: usize,
+}
+
+impl<Key: Ord> Clone for History<Key> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes,
+            top: self.top,
+            len: self.len,
+        }
+    }
+}
+
+impl<Key: Ord> History<Key> {
+    fn new() -> Self {
+        Self {
+            nodes: [None; HISTORY_LEN],
+            top: 0,
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, node: NonNull<Node<Key>>) {
+        self.nodes[self.top] = Some(node);
+        self.top = (self.top + 1) % HISTORY_LEN;
+        self.len = (self.len + 1).min(HISTORY_LEN);
+    }
+
+    fn pop(&mut self) -> Link<Key> {
+        if self.len == 0 {
+            return None;
+        }
+        self.top = (self.top + HISTORY_LEN - 1) % HISTORY_LEN;
+        self.len -= 1;
+        self.nodes[self.top]
+    }
+
+    // Forgets every node, for when the cursor jumps rather than steps.
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl<Key: Ord + Default> CursorState<Key> {
+    fn new(current: Link<Key>, rank: usize) -> Self {
+        Self {
+            current,
+            rank,
+            history: History::new(),
+        }
+    }
+
+    fn valid(&self) -> bool {
+        self.current.is_some()
+    }
+
+    // The head holds no key, so a cursor parked before the first key has none to read.
+    unsafe fn key<'k, const MAX_HEIGHT: usize, A: NodeAllocator>(&self, list: &LinkedListSkipList<'_, Key, MAX_HEIGHT, A>) -> Option<&'k Key> {
+        debug_assert!(self.valid(), "key called on an invalid iterator");
+        debug_assert!(!self.current.is_some_and(|current| list.is_head(current)), "key called on an iterator before the first key");
+        self.current.filter(|current| !list.is_head(*current)).map(|current| &(*current.as_ptr()).key)
+    }
+
+    // Moving past the last key leaves the cursor exhausted, which is invalid.
+    unsafe fn next<'k>(&mut self) -> Option<&'k Key> {
+        let current = self.current?;
+        self.history.push(current);
+        self.current = (*current.as_ptr()).next(0);
+        self.rank += 1;
+        self.current.map(|current| &(*current.as_ptr()).key)
+    }
+
+    fn advance(&mut self) {
+        debug_assert!(self.valid(), "advance called on an invalid iterator");
+        if let Some(current) = self.current {
+            self.history.push(current);
+            self.current = unsafe { (*current.as_ptr()).next(0) };
+            self.rank += 1;
+        }
+    }
+
+    fn skip_forward(&mut self, n: usize) {
+        self.history.clear();
+        let mut remaining = n;
+        unsafe {
+            while remaining > 0 {
+                let Some(current) = self.current else {
+                    return;
+                };
+                // Take the tallest link out of the current node which doesn't overshoot.
+                let current = current.as_ptr();
+                let level = (0..(*current).height()).rev().find(|&level| {
+                    (*current).next(level).is_some() && (*current).span(level) <= remaining
+                });
+                match level {
+                    Some(level) => {
+                        remaining -= (*current).span(level);
+                        self.rank += (*current).span(level);
+                        self.current = (*current).next(level);
+                    }
+                    None => self.current = None, // Ran off the end of the list.
+                }
+            }
+        }
+    }
+
+    // Steps back to the node the cursor last stepped forward from while it is in the history.
+    // Otherwise, after a jump or more than `HISTORY_LEN` steps back, the predecessor is searched for
+    // from the head at O(log n) per step. Stepping back from the first key leaves the cursor invalid.
+    fn prev<const MAX_HEIGHT: usize, A: NodeAllocator>(&mut self, list: &LinkedListSkipList<'_, Key, MAX_HEIGHT, A>) -> Result<(), SkipListError> {
+        debug_assert!(self.valid(), "prev called on an invalid iterator");
+        let Some(current) = self.current else {
+            return Ok(());
+        };
+        let previous = match self.history.pop() {
+            Some(previous) => previous,
+            None if list.is_head(current) => list.head,
+            None => list.find_last_before(|key| key < unsafe { &(*current.as_ptr()).key }),
+        };
+        self.current = (!list.is_head(previous)).then_some(previous);
+        self.rank = self.rank.saturating_sub(1);
+        Ok(())
+    }
+
+    fn seek<const MAX_HEIGHT: usize, A: NodeAllocator>(&mut self, list: &LinkedListSkipList<'_, Key, MAX_HEIGHT, A>, target: &Key) -> Result<bool, SeekError> {
+        let Some((target_node, rank)) = list.find_equal_or_greater_then(target) else {
+            return Err(if list.is_empty() { SeekError::Empty } else { SeekError::AboveMaximum });
+        };
+        self.history.clear();
+        self.current = Some(target_node);
+        self.rank = rank;
+        Ok(unsafe { &(*target_node.as_ptr()).key } == target)
+    }
+
+    fn seek_exact<const MAX_HEIGHT: usize, A: NodeAllocator>(&mut self, list: &LinkedListSkipList<'_, Key, MAX_HEIGHT, A>, target: &Key) -> Result<bool, SeekError> {
+        let Some((target_node, rank)) = list.find_equal_or_greater_then(target) else {
+            return Err(if list.is_empty() { SeekError::Empty } else { SeekError::AboveMaximum });
+        };
+        if unsafe { &(*target_node.as_ptr()).key } == target {
+            self.history.clear();
+            self.current = Some(target_node);
+            self.rank = rank;
+            return Ok(true);
+        }
+        if unsafe { list.head_next(0) } == Some(target_node) {
+            return Err(SeekError::BelowMinimum);
+        }
+        Ok(false)
+    }
+
+    fn seek_for_prev<const MAX_HEIGHT: usize, A: NodeAllocator>(&mut self, list: &LinkedListSkipList<'_, Key, MAX_HEIGHT, A>, target: &Key) -> Result<(), SkipListError> {
+        let (target_node, rank) = list.find_last_before_ranked(|key| key <= target);
+        if list.is_head(target_node) {
+            return Err(SkipListError::OutOfRange);
+        }
+        self.history.clear();
+        self.current = Some(target_node);
+        self.rank = rank;
+        Ok(())
+    }
+
+    // Lands on the smallest key, unlike `reset` which stops on the head before it.
+    fn seek_to_first<const MAX_HEIGHT: usize, A: NodeAllocator>(&mut self, list: &LinkedListSkipList<'_, Key, MAX_HEIGHT, A>) {
+        self.history.clear();
+        self.current = unsafe { list.head_next(0) };
+        self.rank = 1;
+    }
+
+    // The head sits before the first key, so stepping forward from it reaches the smallest key.
+    fn reset<const MAX_HEIGHT: usize, A: NodeAllocator>(&mut self, list: &LinkedListSkipList<'_, Key, MAX_HEIGHT, A>) {
+        self.history.clear();
+        self.current = Some(list.head);
+        self.rank = 0;
+    }
+
+    fn seek_to_last<const MAX_HEIGHT: usize, A: NodeAllocator>(&mut self, list: &LinkedListSkipList<'_, Key, MAX_HEIGHT, A>) -> Result<(), SkipListError> {
+        let (last, rank) = list.find_last_before_ranked(|_| true);
+        self.history.clear();
+        self.current = (!list.is_head(last)).then_some(last);
+        self.rank = rank;
+        Ok(())
+    }
+}
+
+/// A read only view of a list, created by `LinkedListSkipList::view`, which can be shared between
+/// threads. The list cannot be modified while the view borrows it, so readers on any number of
+/// threads only ever follow links which stay put.
+pub struct ImmutableView<'v, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator = Global> {
+    list: &'v LinkedListSkipList<'a, Key, MAX_HEIGHT, A>,
+}
+
+// Safety: reads through the view only touch the nodes, which nothing modifies while the view
+// exists, and the metrics sink, clock and prefix extractor, which are required to be Sync.
+unsafe impl<'v, 'a, Key: Ord + Default + Sync, const MAX_HEIGHT: usize, A: NodeAllocator + Sync> Sync for ImmutableView<'v, 'a, Key, MAX_HEIGHT, A> {}
+unsafe impl<'v, 'a, Key: Ord + Default + Sync, const MAX_HEIGHT: usize, A: NodeAllocator + Sync> Send for ImmutableView<'v, 'a, Key, MAX_HEIGHT, A> {}
+
+impl<'v, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> Clone for ImmutableView<'v, 'a, Key, MAX_HEIGHT, A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'v, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> Copy for ImmutableView<'v, 'a, Key, MAX_HEIGHT, A> {}
+
+impl<'v, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> ImmutableView<'v, 'a, Key, MAX_HEIGHT, A> {
+    /// Returns the key in the list equal to `key`, unless it has expired.
+    pub fn get(&self, key: &Key) -> Option<&'v Key> {
+        let node = self.list.find_equal(key);
+        unsafe { node.filter(|node| !self.list.is_expired(*node)).map(|node| &(*node.as_ptr()).key) }
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Returns an iterator over every key in ascending order.
+    pub fn iter(&self) -> Iter<'v, Key> {
+        self.list.iter()
+    }
+
+    /// Returns the keys within `range` in ascending order.
+    pub fn range<R: RangeBounds<Key> + 'v>(&self, range: R) -> impl Iterator<Item = &'v Key> + 'v {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.list.find_first_not_before(|key| key < start),
+            Bound::Excluded(start) => self.list.find_first_not_before(|key| key <= start),
+            Bound::Unbounded => unsafe { self.list.head_next(0) },
+        };
+        unsafe { self.list.iter_at(start) }.take_while(move |key| match range.end_bound() {
+            Bound::Included(end) => *key <= end,
+            Bound::Excluded(end) => *key < end,
+            Bound::Unbounded => true,
+        })
+    }
+}
+
+/// A list which can no longer be written, with its upper levels packed into a sorted array for
+/// faster searches. Created by `LinkedListSkipList::freeze`.
+pub struct FrozenSkipList<'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator = Global> {
+    list: LinkedListSkipList<'a, Key, MAX_HEIGHT, A>,
+    // Every key on `index_level` in ascending order, and the node holding each.
+    index_keys: Vec<Key>,
+    index_nodes: Vec<NonNull<Node<Key>>>,
+    // The lowest packed level. Searches continue through the links on the levels below it.
+    index_level: usize,
+}
+
+// The list is never written once frozen, so readers on any number of threads only follow links
+// which stay put, as through an `ImmutableView`.
+unsafe impl<'a, Key: Ord + Default + Sync, const MAX_HEIGHT: usize, A: NodeAllocator + Sync> Sync for FrozenSkipList<'a, Key, MAX_HEIGHT, A> {}
+unsafe impl<'a, Key: Ord + Default + Send, const MAX_HEIGHT: usize, A: NodeAllocator + Send> Send for FrozenSkipList<'a, Key, MAX_HEIGHT, A> {}
+
+impl<'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> FrozenSkipList<'a, Key, MAX_HEIGHT, A> {
+    /// Returns the key equal to `key`, unless it has expired.
+    pub fn get(&self, key: &Key) -> Option<&Key> {
+        let node = self.find_equal(key)?;
+        unsafe { (!self.list.is_expired(node)).then(|| &(*node.as_ptr()).key) }
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Returns the number of keys in the packed index.
+    pub fn index_len(&self) -> usize {
+        self.index_keys.len()
+    }
+
+    /// Returns an iterator over every key in ascending order.
+    pub fn iter(&self) -> Iter<'_, Key> {
+        self.list.iter()
+    }
+
+    /// Drops the index and returns the list, which can be written again.
+    pub fn thaw(self) -> LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {
+        self.list
+    }
+
+    // binary searches the index for the last packed key <= `key`, then follows the links down
+    // from it.
+    fn find_equal(&self, key: &Key) -> Link<Key> {
+        let slot = self.index_keys.partition_point(|indexed| indexed <= key);
+        let mut current_node = match slot.checked_sub(1) {
+            Some(slot) if self.index_keys[slot] == *key => return Some(self.index_nodes[slot]),
+            Some(slot) => self.index_nodes[slot],
+            None => self.list.head,
+        };
+        unsafe {
+            for level in (0..self.index_level).rev() {
+                while let Some(next_node) = (*current_node.as_ptr()).next(level) {
+                    match (*next_node.as_ptr()).key.cmp(key) {
+                        Ordering::Less => current_node = next_node,
+                        Ordering::Equal => return Some(next_node),
+                        Ordering::Greater => break,
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Borrowing iterator over the keys of a list, created by `LinkedListSkipList::iter`. Iterating
+/// from the back searches for each predecessor from the head, costing O(log n) per step.
+pub struct Iter<'b, Key: Ord> {
+    // Next node to yield from the front.
+    front: Link<Key>,
+    // Node after the last one to yield from the back, None being the end of the list.
+    back: Link<Key>,
+    head: NonNull<Node<Key>>,
+    height: usize,
+    _marker: PhantomData<&'b Key>,
+}
+
+impl<'b, Key: Ord> Iter<'b, Key> {
+    // Caller must ensure `front` is a node of the list starting at `head` borrowed for 'b.
+    unsafe fn new(front: Link<Key>, head: NonNull<Node<Key>>, height: usize) -> Self {
+        Self {
+            front,
+            back: None,
+            head,
+            height,
+            _marker: PhantomData,
+        }
+    }
+
+    // find the node before `node`, or the last node of the list when `node` is None.
+    unsafe fn predecessor(&self, node: Link<Key>) -> NonNull<Node<Key>> {
+        let mut current_node = self.head;
+        for search_level in (0..self.height).rev() {
+            while let Some(next_node) = (*current_node.as_ptr()).next(search_level) {
+                if node.is_some_and(|node| (*next_node.as_ptr()).key >= (*node.as_ptr()).key) {
+                    break;
+                }
+                current_node = next_node;
+            }
+        }
+        current_node
+    }
+}
+
+impl<'b, Key: Ord> Iterator for Iter<'b, Key> {
+    type Item = &'b Key;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        unsafe {
+            let node = self.front?.as_ptr();
+            self.front = (*node).next(0);
+            Some(&(*node).key)
+        }
+    }
+}
+
+impl<'b, Key: Ord> DoubleEndedIterator for Iter<'b, Key> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        unsafe {
+            let node = self.predecessor(self.back);
+            self.back = Some(node);
+            Some(&(*node.as_ptr()).key)
+        }
+    }
+}
+
+/// Iterator over the live keys visible to a snapshot, created by
+/// `LinkedListSkipList::iter_visible`.
+pub struct VisibleIter<'b, Key: Ord, S, T> {
+    current: Link<Key>,
+    snapshot_seq: u64,
+    now: u64,
+    seq: S,
+    is_tombstone: T,
+    _marker: PhantomData<&'b Key>,
+}
+
+impl<'b, Key: Ord, S: Fn(&Key) -> u64, T: Fn(&Key) -> bool> Iterator for VisibleIter<'b, Key, S, T> {
+    type Item = &'b Key;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        unsafe {
+            while let Some(node) = self.current {
+                let node = &*node.as_ptr();
+                self.current = node.next(0);
+                let expired = node.expires_at.is_some_and(|expires_at| expires_at <= self.now);
+                if !expired && (self.seq)(&node.key) <= self.snapshot_seq && !(self.is_tombstone)(&node.key) {
+                    return Some(&node.key);
+                }
+            }
+            None
+        }
+    }
+}
+
+/// A handle to a node of a list, from `seek_node`, `min_node` or `max_node`, for walking the
+/// list from a node without searching again. Expired keys are not skipped. The handle does not
+/// borrow the list, so nothing stops the node being freed while it is held.
+///
+/// # Safety
+/// Each method requires that the node is still linked in a list which is alive, and that nothing
+/// writes to that list for as long as the call or any reference it returned is in use. Removing
+/// the node or dropping its list leaves the handle dangling. Inserts and `rebuild` do not free
+/// nodes, though an insert may replace the key of the node in place.
+pub struct NodeRef<Key: Ord> {
+    node: NonNull<Node<Key>>,
+}
+
+impl<Key: Ord> NodeRef<Key> {
+    /// Returns the key held by the node.
+    ///
+    /// # Safety
+    /// See `NodeRef`. The key must not be used past the next write to the list.
+    pub unsafe fn key<'n>(&self) -> &'n Key {
+        &(*self.node.as_ptr()).key
+    }
+
+    /// Returns the node after this one on the bottom level, or None if it is the last.
+    ///
+    /// # Safety
+    /// See `NodeRef`.
+    pub unsafe fn next(&self) -> Option<NodeRef<Key>> {
+        (*self.node.as_ptr()).next(0).map(|node| NodeRef { node })
+    }
+
+    /// Returns the number of levels the node is linked on.
+    ///
+    /// # Safety
+    /// See `NodeRef`.
+    pub unsafe fn height(&self) -> usize {
+        (*self.node.as_ptr()).height()
+    }
+}
+
+impl<Key: Ord> Clone for NodeRef<Key> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Key: Ord> Copy for NodeRef<Key> {}
+
+impl<Key: Ord> PartialEq for NodeRef<Key> {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node
+    }
+}
+
+impl<Key: Ord> Eq for NodeRef<Key> {}
+
+impl<Key: Ord> Debug for NodeRef<Key> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("NodeRef").field(&self.node).finish()
+    }
+}
+
+struct Node<Key: Ord> {
+    key: Key,
+    links: Vec<Link<Key>>,
+    // Number of level 0 positions each link moves forward. Only meaningful for links which are set.
+    spans: Vec<usize>,
+    // Clock tick at which the key expires, None if it never does.
+    expires_at: Option<u64>,
+}
+
+type Link<Key> = Option<NonNull<Node<Key>>>;
+
+// hints that `ptr` is about to be read. Does nothing on targets without a prefetch instruction.
+#[inline(always)]
+fn prefetch_read<T>(ptr: *const T) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        std::arch::x86_64::_mm_prefetch::<{ std::arch::x86_64::_MM_HINT_T0 }>(ptr.cast());
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = ptr;
+}
+
+// The links and spans out of the nodes before a node being inserted, as they were before the
+// insert. If the insert unwinds before it is forgotten, the guard puts them back and frees the
+// node, so a panic never leaves a node linked on some levels but not others.
+struct Splice<'s, Key: Ord + Default, A: NodeAllocator, const MAX_HEIGHT: usize> {
+    node: NonNull<Node<Key>>,
+    previous: &'s [NonNull<Node<Key>>; MAX_HEIGHT],
+    saved: [(Link<Key>, usize); MAX_HEIGHT],
+    levels: usize,
+    alloc: &'s A,
+}
+
+impl<'s, Key: Ord + Default, A: NodeAllocator, const MAX_HEIGHT: usize> Splice<'s, Key, A, MAX_HEIGHT> {
+    // Saves the first `levels` links out of `previous`, which must be valid nodes.
+    unsafe fn new(node: NonNull<Node<Key>>, previous: &'s [NonNull<Node<Key>>; MAX_HEIGHT], levels: usize, alloc: &'s A) -> Self {
+        let mut saved = [(None, 0); MAX_HEIGHT];
+        for (i, previous_node) in previous.iter().enumerate().take(levels) {
+            let previous_node = previous_node.as_ptr();
+            saved[i] = ((*previous_node).next(i), (*previous_node).span(i));
+        }
+        Self { node, previous, saved, levels, alloc }
+    }
+}
+
+impl<'s, Key: Ord + Default, A: NodeAllocator, const MAX_HEIGHT: usize> Drop for Splice<'s, Key, A, MAX_HEIGHT> {
+    fn drop(&mut self) {
+        unsafe {
+            for (i, previous_node) in self.previous.iter().enumerate().take(self.levels) {
+                let previous_node = previous_node.as_ptr();
+                let (next_node, span) = self.saved[i];
+                (*previous_node).set_next(i, next_node);
+                (*previous_node).set_span(i, span);
+            }
+            Node::free(self.node, self.alloc);
+        }
+    }
+}
+
+// A node on each level and the rank of each, such as the nodes before a position in the list.
+type Path<Key, const MAX_HEIGHT: usize> = ([NonNull<Node<Key>>; MAX_HEIGHT], [usize; MAX_HEIGHT]);
+
+impl<Key: Ord + Default> Node<Key> {
+    fn new_link<A: NodeAllocator>(key: Key, height: usize, alloc: &A) -> NonNull<Node<Key>> {
+        let node = alloc.allocate(Layout::new::<Node<Key>>()).cast::<Node<Key>>();
+        unsafe {
+            node.as_ptr().write(Node {
+                key,
+                links: vec![None; height],
+                spans: vec![0; height],
+                expires_at: None,
+            });
+        }
+        node
+    }
+
+    fn new_head<A: NodeAllocator>(height: usize, alloc: &A) -> NonNull<Node<Key>> {
+        Self::new_link(Key::default(), height, alloc)
+    }
+
+    // Moves the key out of the node and returns its memory to `alloc`. The node must have been
+    // allocated from `alloc` and not be reachable from anywhere else.
+    unsafe fn into_key<A: NodeAllocator>(node: NonNull<Node<Key>>, alloc: &A) -> Key {
+        let Node { key, .. } = node.as_ptr().read();
+        alloc.deallocate(node.cast(), Layout::new::<Node<Key>>());
+        key
+    }
+
+    // Drops the node and returns its memory to `alloc`. The node must have been allocated from
+    // `alloc` and not be reachable from anywhere else.
+    unsafe fn free<A: NodeAllocator>(node: NonNull<Node<Key>>, alloc: &A) {
+        std::ptr::drop_in_place(node.as_ptr());
+        alloc.deallocate(node.cast(), Layout::new::<Node<Key>>());
+    }
+
+    // As `free` for a node whose key has already been moved out.
+    unsafe fn free_released<A: NodeAllocator>(node: NonNull<Node<Key>>, alloc: &A) {
+        std::ptr::drop_in_place(&mut (*node.as_ptr()).links);
+        std::ptr::drop_in_place(&mut (*node.as_ptr()).spans);
+        alloc.deallocate(node.cast(), Layout::new::<Node<Key>>());
+    }
+}
+
+impl<Key: Ord> Node<Key> {
+    #[inline(always)]
+    fn height(&self) -> usize {
+        self.links.len()
+    }
+
+    // Every level accessor checks the level in debug builds so a splicing bug panics with the
+    // level and height rather than a bare index out of bounds, which release builds still give.
+    #[inline(always)]
+    fn check_level(&self, n: usize) {
+        debug_assert!(n < self.height(), "level {} exceeds node height {}", n, self.height());
+    }
+
+    #[inline(always)]
+    fn set_span(&mut self, n: usize, span: usize) {
+        self.check_level(n);
+        self.spans[n] = span;
+    }
+
+    #[inline(always)]
+    fn span(&self, n: usize) -> usize {
+        self.check_level(n);
+        self.spans[n]
+    }
+
+    #[inline(always)]
+    fn set_next(&mut self, n: usize, x: Link<Key>) {
+        self.check_level(n);
+        self.links[n] = x;
+    }
+
+    #[inline(always)]
+    fn next(&self, n: usize) -> Link<Key> {
+        self.check_level(n);
+        self.links[n]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memtable::skiplist::{AsciiCaseInsensitive, TransformedKey};
+
+    // Mirrors every write onto a BTreeSet and checks the list agrees with it after each one. Tests
+    // opt in by writing through the oracle in place of the list.
+    struct OracleSkipList<Key: Ord + Default> {
+        list: LinkedListSkipList<'static, Key, { 2_usize.pow(6) }>,
+        oracle: std::collections::BTreeSet<Key>,
+    }
+
+    impl<Key: Ord + Default + Clone + Debug> OracleSkipList<Key> {
+        fn new() -> Self {
+            Self {
+                list: LinkedListSkipList::new(),
+                oracle: std::collections::BTreeSet::new(),
+            }
+        }
+
+        fn insert(&mut self, key: Key) -> Option<Key> {
+            let replaced = self.list.replace(key.clone());
+            assert_eq!(replaced, self.oracle.replace(key));
+            self.check();
+            replaced
+        }
+
+        fn remove(&mut self, key: &Key) -> bool {
+            let removed = self.list.remove(key);
+            assert_eq!(removed, self.oracle.remove(key), "remove {:?}", key);
+            self.check();
+            removed
+        }
+
+        fn remove_sorted(&mut self, keys: &[Key]) -> usize {
+            let removed = self.list.remove_sorted(keys.iter().cloned());
+            assert_eq!(removed, keys.iter().filter(|key| self.oracle.remove(*key)).count());
+            self.check();
+            removed
+        }
+
+        fn get(&self, key: &Key) -> Option<&Key> {
+            let found = self.list.get(key);
+            assert_eq!(found, self.oracle.get(key), "get {:?}", key);
+            assert_eq!(self.list.contains(key), found.is_some(), "contains {:?}", key);
+            found
+        }
+
+        fn check(&self) {
+            assert_eq!(self.list.len(), self.oracle.len());
+            assert!(self.list.iter().eq(self.oracle.iter()), "{:?} != {:?}", self.list, self.oracle);
+            assert!(self.list.iter().rev().eq(self.oracle.iter().rev()));
+            assert_eq!(self.list.min(), self.oracle.first());
+            assert_eq!(self.list.max(), self.oracle.last());
+        }
+    }
+
+    #[test]
+    fn test_oracle_random_ops() {
+        seed_test_rng();
+        let mut list = OracleSkipList::new();
+        for _i in 0..10_000 {
+            let key = fastrand::i32(0..500);
+            match fastrand::u8(..10) {
+                0..=4 => {
+                    list.insert(key);
+                }
+                5..=6 => {
+                    list.remove(&key);
+                }
+                7 => {
+                    let mut keys: Vec<i32> = (0..fastrand::usize(..8)).map(|_| fastrand::i32(0..500)).collect();
+                    keys.sort();
+                    keys.dedup();
+                    list.remove_sorted(&keys);
+                }
+                _ => {
+                    list.get(&key);
+                }
+            }
+        }
+        assert_invariants(&list.list);
+    }
+
+    #[test]
+    fn test_seq_insert() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(_i);
+            assert!(list.contains(&_i));
+        }
+    }
+
+    #[test]
+    fn test_insert_random_insert() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            let val = fastrand::i32(0..1000);
+            list.insert(val);
+            assert!(list.contains(&val));
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_towers() {
+        seed_test_rng();
+        let keys: Vec<i32> = (0..300).map(|_| fastrand::i32(0..10_000)).collect();
+        let build = |seed: u64| {
+            let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new().with_seed(seed);
+            for key in &keys {
+                list.insert(*key);
+            }
+            list.remove(&keys[0]);
+            list.dump()
+        };
+        assert_eq!(build(7), build(7));
+        assert_ne!(build(7), build(8));
+
+        // Lists left to the default seed are laid out the same in tests too.
+        let mut first: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut second: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for key in &keys {
+            first.insert(*key);
+            second.insert(*key);
+        }
+        assert_eq!(first.dump(), second.dump());
+    }
+
+    #[test]
+    fn test_into_iterator() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..100 {
+            list.insert(_i);
+        }
+
+        let iter = list.into_iter();
+        for (expected_val, _i) in (0..).zip(iter) {
+            assert_eq!(&expected_val, _i);
+        }
+    }
+
+    #[test]
+    fn test_iterator_seek() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..100 {
+            list.insert(_i);
+        }
+        let mut iter = list.into_iter();
+        iter.seek(&50).unwrap();
+        assert_eq!(iter.key().unwrap(), &50);
+    }
+
+    #[test]
+    fn test_iterator_skip_forward() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(fastrand::i32(0..1000));
+        }
+        for _i in 0..1000 {
+            list.insert(_i);
+        }
+        let mut iter = list.into_iter();
+        iter.seek_to_first();
+        assert_eq!(iter.key().unwrap(), &0);
+        iter.skip_forward(499);
+        assert_eq!(iter.key().unwrap(), &499);
+
+        iter.skip_forward(1);
+        assert_eq!(iter.key().unwrap(), &500);
+        iter.skip_forward(0);
+        assert_eq!(iter.key().unwrap(), &500);
+        iter.skip_forward(499);
+        assert_eq!(iter.key().unwrap(), &999);
+    }
+
+    #[test]
+    fn test_iterator_skip_forward_past_end() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..100 {
+            list.insert(_i);
+        }
+        let mut iter = list.into_iter();
+        iter.seek(&90).unwrap();
+        iter.skip_forward(10);
+        assert!(!iter.valid());
+
+        iter.seek_to_first();
+        iter.skip_forward(1000);
+        assert!(!iter.valid());
+    }
+
+    #[test]
+    fn test_cursor_advance_by() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..100 {
+            list.insert(_i * 2);
+        }
+        let mut cursor = list.cursor();
+        assert_eq!(cursor.advance_by(1), 1);
+        assert_eq!(cursor.key(), Some(&0));
+        assert_eq!(cursor.advance_by(0), 0);
+        assert_eq!(cursor.key(), Some(&0));
+        assert_eq!(cursor.advance_by(49), 49);
+        assert_eq!(cursor.key(), Some(&98));
+        assert_eq!(cursor.position(), 49);
+        cursor.prev().unwrap();
+        assert_eq!(cursor.key(), Some(&96));
+
+        // Ten pages of ten keys each, the last page ending at the last key.
+        cursor.reset();
+        let mut pages = 0;
+        while cursor.advance_by(10) == 10 {
+            pages += 1;
+        }
+        assert_eq!(pages, 10);
+        assert!(!cursor.valid());
+
+        // Past the end only the keys left are counted.
+        cursor.seek(&180).unwrap();
+        assert_eq!(cursor.advance_by(50), 9);
+        assert!(!cursor.valid());
+        assert_eq!(cursor.advance_by(5), 0);
+    }
+
+    #[test]
+    fn test_iterator_seek_out_of_range() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 10..100 {
+            list.insert(_i);
+        }
+        let mut iter = list.into_iter();
+        iter.seek(&50).unwrap();
+        assert_eq!(iter.seek(&100), Err(SeekError::AboveMaximum));
+        assert_eq!(iter.key().unwrap(), &50);
+
+        iter.seek(&0).unwrap();
+        assert_eq!(iter.key().unwrap(), &10);
+    }
+
+    #[test]
+    fn test_as_std_iter() {
+        fn small_evens<'k, I: SkipListIterator<&'k i32>>(iter: &mut I) -> Vec<i32> {
+            iter.as_std_iter().filter(|key| *key % 2 == 0).map(|key| key * 10).take(3).collect()
+        }
+
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 1..=20 {
+            list.insert(_i);
+        }
+        let mut cursor = list.cursor();
+        assert_eq!(small_evens(&mut cursor), vec![20, 40, 60]);
+        // Carries on from where the adaptor left the cursor.
+        assert_eq!(cursor.key(), Some(&6));
+        assert_eq!(small_evens(&mut cursor), vec![80, 100, 120]);
+        assert_eq!(cursor.as_std_iter().count(), 8);
+        assert!(!cursor.valid());
+        assert_eq!(cursor.as_std_iter().next(), None);
+
+        cursor.reset();
+        let odd: Vec<i32> = cursor.as_std_iter().filter(|key| *key % 2 == 1).copied().collect();
+        assert_eq!(odd, (1..=20).step_by(2).collect::<Vec<_>>());
+        let mut iter = list.into_iter();
+        assert_eq!(iter.as_std_iter().map(|key| key * 2).sum::<i32>(), 420);
+    }
+
+    #[test]
+    fn test_cursor_position() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut keys: Vec<i32> = (0..1000).collect();
+        fastrand::shuffle(&mut keys);
+        for key in keys {
+            list.insert(key);
+        }
+        let targets: Vec<i32> = (0..1000).collect();
+        let mut cursor = list.cursor();
+        for target in &targets {
+            cursor.seek(target).unwrap();
+            assert_eq!(cursor.position(), *target as usize);
+        }
+        cursor.seek(&500).unwrap();
+        for position in 501..1000 {
+            cursor.advance();
+            assert_eq!(cursor.position(), position);
+        }
+
+        cursor.reset();
+        assert_eq!(cursor.next(), Some(&0));
+        assert_eq!(cursor.position(), 0);
+        cursor.skip_forward(300);
+        assert_eq!((cursor.key(), cursor.position()), (Some(&300), 300));
+        cursor.prev().unwrap();
+        cursor.prev().unwrap();
+        assert_eq!((cursor.key(), cursor.position()), (Some(&298), 298));
+        cursor.seek_for_prev(&700).unwrap();
+        assert_eq!(cursor.position(), 700);
+        cursor.seek_to_last().unwrap();
+        assert_eq!(cursor.position(), 999);
+        assert_eq!(cursor.seek_exact(&42), Ok(true));
+        assert_eq!(cursor.position(), 42);
+        let mut cursor = list.cursor_from(Bound::Excluded(&9));
+        assert_eq!(cursor.next(), Some(&10));
+        assert_eq!(cursor.position(), 10);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_stale_cursor_detected() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..100 {
+            list.insert(_i);
+        }
+        let mut cursor = list.cursor();
+        cursor.seek(&50).unwrap();
+        // Carry the cursor's position past the borrow, which only code with access to its fields
+        // can, then free the node it is at.
+        let (state, generation) = (cursor.state.clone(), cursor.generation);
+        assert!(list.remove(&50));
+
+        let mut stale = SkipListCursor { list: &list, state, generation };
+        assert!(catch_unwind(AssertUnwindSafe(|| stale.key())).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| stale.clone().advance())).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| stale.clone().next())).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| stale.clone().prev())).is_err());
+        // A seek positions the cursor afresh.
+        stale.seek(&50).unwrap();
+        assert_eq!(stale.key(), Some(&51));
+        assert_eq!(stale.next(), Some(&52));
+
+        // Replacing a key in place moves no links.
+        let generation = list.generation;
+        list.insert(60);
+        assert_eq!(list.generation, generation);
+        list.insert(1000);
+        assert_ne!(list.generation, generation);
+    }
+
+    #[test]
+    fn test_seek_errors() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 1..=10 {
+            list.insert(_i * 10);
+        }
+        let mut cursor = list.cursor();
+        assert_eq!(cursor.seek(&40), Ok(true));
+        assert_eq!(cursor.seek(&45), Ok(false));
+        assert_eq!(cursor.key(), Some(&50));
+        assert_eq!(cursor.seek(&5), Ok(false));
+        assert_eq!(cursor.key(), Some(&10));
+        assert_eq!(cursor.seek(&101), Err(SeekError::AboveMaximum));
+        assert_eq!(cursor.key(), Some(&10));
+
+        assert_eq!(cursor.seek_exact(&70), Ok(true));
+        assert_eq!(cursor.seek_exact(&75), Ok(false));
+        assert_eq!(cursor.key(), Some(&70));
+        assert_eq!(cursor.seek_exact(&5), Err(SeekError::BelowMinimum));
+        assert_eq!(cursor.seek_exact(&101), Err(SeekError::AboveMaximum));
+        assert_eq!(cursor.key(), Some(&70));
+        assert_eq!(cursor.seek_exact(&10), Ok(true));
+        assert_eq!(cursor.seek_exact(&100), Ok(true));
+
+        let empty: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut cursor = empty.cursor();
+        assert_eq!(cursor.seek(&5), Err(SeekError::Empty));
+        assert_eq!(cursor.seek_exact(&5), Err(SeekError::Empty));
+    }
+
+    #[test]
+    fn test_iterator_seek_empty() {
+        let list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut iter = list.into_iter();
+        assert_eq!(iter.seek(&0), Err(SeekError::Empty));
+        assert_eq!(iter.seek_exact(&0), Err(SeekError::Empty));
+    }
+
+    #[test]
+    fn test_iterator_reverse() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..100 {
+            list.insert(_i * 2);
+        }
+        let mut iter = list.into_iter();
+        iter.seek(&50).unwrap();
+        assert!(iter.supports_reverse());
+        iter.prev().unwrap();
+        assert_eq!(iter.key().unwrap(), &48);
+        iter.seek_for_prev(&11).unwrap();
+        assert_eq!(iter.key().unwrap(), &10);
+        iter.seek_for_prev(&12).unwrap();
+        assert_eq!(iter.key().unwrap(), &12);
+        assert_eq!(iter.seek_for_prev(&-1), Err(SkipListError::OutOfRange));
+        assert_eq!(iter.key().unwrap(), &12);
+        iter.seek_to_last().unwrap();
+        assert_eq!(iter.key().unwrap(), &198);
+        iter.seek_to_first();
+        assert_eq!(iter.key().unwrap(), &0);
+        iter.prev().unwrap();
+        assert!(!iter.valid());
+
+        let empty: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut cursor = empty.cursor();
+        cursor.seek_to_last().unwrap();
+        assert!(!cursor.valid());
+    }
+
+    #[test]
+    fn test_cursor_prev_bounded_history() {
+        let mut list: LinkedListSkipList<CountedKey, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(CountedKey(_i));
+        }
+        let mut cursor = list.cursor();
+        assert!(cursor.by_ref().take(1000).map(|key| key.0).eq(0..1000));
+        assert_eq!(cursor.key(), Some(&CountedKey(999)));
+        assert_eq!(cursor.state.history.len, HISTORY_LEN);
+
+        // Steps within the history are taken without comparing keys, the rest search.
+        let buffered = count_comparisons(|| {
+            for _i in (999 - HISTORY_LEN as i32..999).rev() {
+                cursor.prev().unwrap();
+                assert_eq!(cursor.key(), Some(&CountedKey(_i)));
+            }
+        });
+        assert_eq!(buffered, 0);
+        let searched = count_comparisons(|| {
+            for _i in (0..999 - HISTORY_LEN as i32).rev() {
+                cursor.prev().unwrap();
+                assert_eq!(cursor.key(), Some(&CountedKey(_i)));
+            }
+        });
+        assert!(searched > 0);
+        assert_eq!(cursor.state.history.len, 0);
+        cursor.prev().unwrap();
+        assert!(!cursor.valid());
+    }
+
+    #[test]
+    fn test_iterator_next_after_end() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..10 {
+            list.insert(_i);
+        }
+        let mut iter = list.into_iter();
+        iter.skip_forward(100);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in (0..100).rev() {
+            list.insert(_i);
+        }
+        assert!(list.iter().copied().eq(0..100));
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+    struct CompositeKey(Vec<u8>);
+
+    impl CompositeKey {
+        fn new(user_id: u32, timestamp: u64) -> Self {
+            let mut bytes = user_id.to_be_bytes().to_vec();
+            bytes.extend_from_slice(&timestamp.to_be_bytes());
+            Self(bytes)
+        }
+    }
+
+    impl AsRef<[u8]> for CompositeKey {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    struct UserIdExtractor;
+
+    impl PrefixExtractor<CompositeKey> for UserIdExtractor {
+        fn prefix<'a>(&self, key: &'a CompositeKey) -> &'a [u8] {
+            &key.0[..4]
+        }
+    }
+
+    #[test]
+    fn test_prefix_iter_extractor() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<CompositeKey, { 2_usize.pow(6) }> =
+            LinkedListSkipList::new().with_prefix_extractor(Box::new(UserIdExtractor));
+        for user_id in 0..10 {
+            for timestamp in 0..50 {
+                list.insert(CompositeKey::new(user_id, fastrand::u64(..)));
+                list.insert(CompositeKey::new(user_id, timestamp));
+            }
+        }
+        let user = 7_u32.to_be_bytes();
+        let entries: Vec<&CompositeKey> = list.prefix_iter(&user).collect();
+        let expected: Vec<&CompositeKey> = list.iter().filter(|key| key.0[..4] == user).collect();
+        assert_eq!(entries.len(), expected.len());
+        assert!(entries.len() >= 50);
+        assert_eq!(entries, expected);
+        assert_eq!(list.prefix_iter(&10_u32.to_be_bytes()).count(), 0);
+    }
+
+    #[test]
+    fn test_prefix_iter_identity() {
+        let mut list: LinkedListSkipList<CompositeKey, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for key in [b"app".to_vec(), b"apple".to_vec(), b"apply".to_vec(), b"ape".to_vec(), b"b".to_vec()] {
+            list.insert(CompositeKey(key));
+        }
+        let entries: Vec<&[u8]> = list.prefix_iter(b"appl").map(|key| key.as_ref()).collect();
+        assert_eq!(entries, vec![&b"apple"[..], &b"apply"[..]]);
+        assert_eq!(list.prefix_iter(b"").count(), 5);
+    }
+
+    #[test]
+    fn test_key_size_histogram() {
+        let mut list: LinkedListSkipList<CompositeKey, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert_eq!(list.key_size_histogram::<4>(), [0; 4]);
+        for len in [0, 1, 2, 3, 4, 7, 8, 100, 1000] {
+            list.insert(CompositeKey(vec![b'k'; len]));
+        }
+        assert_eq!(list.key_size_histogram::<6>(), [1, 1, 2, 2, 1, 2]);
+        assert_eq!(list.key_size_histogram::<12>(), [1, 1, 2, 2, 1, 0, 0, 1, 0, 0, 1, 0]);
+        assert_eq!(list.key_size_histogram::<1>(), [9]);
+        assert_eq!(list.key_size_histogram::<0>(), []);
+    }
+
+    #[test]
+    fn test_closest() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert_eq!(list.closest(&10), None);
+        for key in [10, 20, 30, 50, 100] {
+            list.insert(key);
+        }
+        assert_eq!(list.closest(&30), Some((&30, Ordering::Equal)));
+        assert_eq!(list.closest(&24), Some((&20, Ordering::Less)));
+        assert_eq!(list.closest(&26), Some((&30, Ordering::Greater)));
+        assert_eq!(list.closest(&25), Some((&20, Ordering::Less)));
+        assert_eq!(list.closest(&90), Some((&100, Ordering::Greater)));
+        assert_eq!(list.closest(&5), Some((&10, Ordering::Greater)));
+        assert_eq!(list.closest(&-5), Some((&10, Ordering::Greater)));
+        assert_eq!(list.closest(&200), Some((&100, Ordering::Less)));
+
+        // Distances across the whole range of the type do not overflow.
+        let mut extremes: LinkedListSkipList<i64, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        extremes.insert(i64::MIN);
+        extremes.insert(i64::MAX);
+        assert_eq!(extremes.closest(&0), Some((&i64::MAX, Ordering::Greater)));
+        assert_eq!(extremes.closest(&-1), Some((&i64::MIN, Ordering::Less)));
+        assert_eq!(extremes.closest(&(i64::MIN + 1)), Some((&i64::MIN, Ordering::Less)));
+        assert_eq!(extremes.closest(&(i64::MAX - 1)), Some((&i64::MAX, Ordering::Greater)));
+        let mut unsigned: LinkedListSkipList<u8, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        unsigned.insert(0);
+        unsigned.insert(u8::MAX);
+        assert_eq!(unsigned.closest(&127), Some((&0, Ordering::Less)));
+        assert_eq!(unsigned.closest(&128), Some((&u8::MAX, Ordering::Greater)));
+    }
+
+    #[test]
+    fn test_contains_all_any() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(fastrand::i32(0..2000));
+        }
+        for _i in 0..100 {
+            let mut keys: Vec<i32> = (0..fastrand::usize(0..20)).map(|_| fastrand::i32(-10..2010)).collect();
+            if fastrand::bool() {
+                keys.sort();
+            }
+            assert_eq!(list.contains_all(&keys), keys.iter().all(|key| list.contains(key)));
+            assert_eq!(list.contains_any(&keys), keys.iter().any(|key| list.contains(key)));
+        }
+
+        let evens: Vec<i32> = (0..1000).map(|key| key * 2).collect();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for key in &evens {
+            list.insert(*key);
+        }
+        assert!(list.contains_all(&evens));
+        assert!(!list.contains_any(&evens.iter().map(|key| key + 1).collect::<Vec<i32>>()));
+        assert!(list.contains_all(&[]));
+        assert!(!list.contains_any(&[]));
+    }
+
+    thread_local! {
+        static COMPARISONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    // Key which counts how many times it is compared.
+    #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+    struct CountedKey(i32);
+
+    impl Ord for CountedKey {
+        fn cmp(&self, other: &Self) -> Ordering {
+            COMPARISONS.with(|count| count.set(count.get() + 1));
+            self.0.cmp(&other.0)
+        }
+    }
+
+    impl PartialOrd for CountedKey {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    fn count_comparisons<F: FnOnce()>(f: F) -> usize {
+        COMPARISONS.with(|count| count.set(0));
+        f();
+        COMPARISONS.with(|count| count.get())
+    }
+
+    #[test]
+    fn test_contains_any_all_early_exit() {
+        let mut list: LinkedListSkipList<CountedKey, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(CountedKey(_i * 2));
+        }
+        let single = count_comparisons(|| assert!(list.contains_any(&[CountedKey(10)])));
+        let rest: Vec<CountedKey> = [10].into_iter().chain(11..2000).map(CountedKey).collect();
+        assert_eq!(count_comparisons(|| assert!(list.contains_any(&rest))), single);
+
+        let single = count_comparisons(|| assert!(!list.contains_all(&[CountedKey(11)])));
+        let rest: Vec<CountedKey> = (11..2000).map(CountedKey).collect();
+        assert_eq!(count_comparisons(|| assert!(!list.contains_all(&rest))), single);
+    }
+
+    // Orders by the integer part alone while == compares the whole value, like a float wrapper
+    // which orders NaNs as equal to each other.
+    #[derive(Debug, Default, Clone, Copy)]
+    struct InconsistentKey(f64);
+
+    impl PartialEq for InconsistentKey {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for InconsistentKey {}
+
+    impl Ord for InconsistentKey {
+        fn cmp(&self, other: &Self) -> Ordering {
+            (self.0 as i64).cmp(&(other.0 as i64))
+        }
+    }
+
+    impl PartialOrd for InconsistentKey {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "keys compare Equal but are not ==")]
+    fn test_inconsistent_ord_detected() {
+        let mut list: LinkedListSkipList<InconsistentKey, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        list.insert(InconsistentKey(1.0));
+        list.insert(InconsistentKey(2.0));
+        list.insert(InconsistentKey(1.5));
+    }
+
+    // Sink which totals the costs it is sent.
+    #[derive(Default)]
+    struct TotalingSink {
+        searches: std::sync::atomic::AtomicUsize,
+        comparisons: std::sync::atomic::AtomicUsize,
+        level_descents: std::sync::atomic::AtomicUsize,
+        repairs: std::sync::atomic::AtomicUsize,
+    }
+
+    impl MetricsSink for std::sync::Arc<TotalingSink> {
+        fn record_search(&self, comparisons: usize, level_descents: usize) {
+            use std::sync::atomic::Ordering::Relaxed;
+            self.searches.fetch_add(1, Relaxed);
+            self.comparisons.fetch_add(comparisons, Relaxed);
+            self.level_descents.fetch_add(level_descents, Relaxed);
+        }
+
+        fn record_repair(&self, _level: usize) {
+            self.repairs.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_metrics_lookup_cost() {
+        use std::sync::atomic::Ordering::Relaxed;
+        let sink = std::sync::Arc::new(TotalingSink::default());
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new()
+            .with_metrics(Box::new(sink.clone()));
+        let n = 1 << 16;
+        for _i in 0..n {
+            list.insert(_i);
+        }
+        sink.searches.store(0, Relaxed);
+        sink.comparisons.store(0, Relaxed);
+        sink.level_descents.store(0, Relaxed);
+
+        for _i in (0..n).step_by(7) {
+            assert!(list.contains(&_i));
+        }
+        let searches = sink.searches.load(Relaxed);
+        assert_eq!(searches, (0..n).step_by(7).count());
+        let log_n = (n as f64).log2();
+        let comparisons = sink.comparisons.load(Relaxed) as f64 / searches as f64;
+        let level_descents = sink.level_descents.load(Relaxed) as f64 / searches as f64;
+        assert!(comparisons <= 4.0 * log_n, "{} comparisons per lookup", comparisons);
+        assert!(level_descents <= 2.0 * log_n, "{} level descents per lookup", level_descents);
+    }
+
+    #[derive(Default)]
+    struct CountingAllocator {
+        allocated: std::cell::RefCell<std::collections::HashMap<usize, usize>>,
+        deallocated: std::cell::RefCell<std::collections::HashMap<usize, usize>>,
+    }
+
+    unsafe impl NodeAllocator for &CountingAllocator {
+        fn allocate(&self, layout: Layout) -> NonNull<u8> {
+            let ptr = Global.allocate(layout);
+            *self.allocated.borrow_mut().entry(ptr.as_ptr() as usize).or_default() += 1;
+            ptr
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            *self.deallocated.borrow_mut().entry(ptr.as_ptr() as usize).or_default() += 1;
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn test_allocator_frees_every_node() {
+        seed_test_rng();
+        let alloc = CountingAllocator::default();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }, &CountingAllocator> = LinkedListSkipList::new_in(&alloc);
+        for _i in 0..1000 {
+            list.insert(fastrand::i32(0..500));
+        }
+        let nodes = alloc.allocated.borrow().values().sum::<usize>();
+        assert_eq!(nodes, list.estimate_count(&0) + 1); // Includes the head.
+        assert!(alloc.deallocated.borrow().is_empty());
+        drop(list);
+        // Addresses can be reused after being freed, so compare per address counts.
+        assert_eq!(*alloc.allocated.borrow(), *alloc.deallocated.borrow());
+    }
+
+    #[test]
+    fn test_removed_nodes_are_reused() {
+        seed_test_rng();
+        let alloc = CountingAllocator::default();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }, &CountingAllocator> = LinkedListSkipList::new_in(&alloc);
+        for key in 0..100 {
+            list.insert(key * 2);
+        }
+        let cycles = 1_000_000;
+        for _i in 0..cycles {
+            let key = fastrand::i32(0..100) * 2 + 1;
+            list.insert(key);
+            assert!(list.remove(&key));
+        }
+        assert_invariants(&list);
+        let nodes = alloc.allocated.borrow().values().sum::<usize>();
+        assert!(nodes < cycles / 1000, "{} allocations for {} cycles", nodes, cycles);
+        drop(list);
+        assert_eq!(*alloc.allocated.borrow(), *alloc.deallocated.borrow());
+    }
+
+    #[test]
+    fn test_remove() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut expected = std::collections::BTreeSet::new();
+        for _i in 0..1000 {
+            let key = fastrand::i32(0..1000);
+            list.insert(key);
+            expected.insert(key);
+        }
+        for _i in 0..1000 {
+            let key = fastrand::i32(0..1000);
+            assert_eq!(list.remove(&key), expected.remove(&key));
+            assert!(!list.contains(&key));
+        }
+        assert_eq!(list.len(), expected.len());
+        assert!(list.iter().eq(expected.iter()));
+        assert_spans(&list);
+        while let Some(key) = expected.pop_first() {
+            assert!(list.remove(&key));
+        }
+        assert!(list.is_empty());
+        assert_eq!(list.get_max_height(), 0);
+    }
+
+    // Checks every link's span matches the number of level 0 positions it moves forward.
+    fn assert_spans<Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator>(list: &LinkedListSkipList<Key, MAX_HEIGHT, A>) {
+        unsafe {
+            let mut ranks = std::collections::HashMap::new();
+            let mut current = Some(list.head);
+            let mut rank = 0;
+            while let Some(node) = current {
+                ranks.insert(node, rank);
+                rank += 1;
+                current = (*node.as_ptr()).next(0);
+            }
+            for (node, rank) in &ranks {
+                for level in 0..(*node.as_ptr()).height() {
+                    if let Some(next_node) = (*node.as_ptr()).next(level) {
+                        assert_eq!((*node.as_ptr()).span(level), ranks[&next_node] - rank);
+                    }
+                }
+            }
+        }
+    }
+
+    // Asserts every level is sorted, every node is linked on each level its tower reaches, every
+    // span is correct and the cached size and height match the nodes in the list.
+    fn assert_invariants<Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator>(list: &LinkedListSkipList<Key, MAX_HEIGHT, A>) {
+        assert_spans(list);
+        unsafe {
+            let mut heights = Vec::new();
+            let mut current = (*list.head.as_ptr()).next(0);
+            while let Some(node) = current {
+                heights.push((*node.as_ptr()).height());
+                current = (*node.as_ptr()).next(0);
+            }
+            assert!((*list.head.as_ptr()).height() >= list.height(), "the head is shorter than the list");
+            for level in 0..MAX_HEIGHT {
+                let mut current = list.head_next(level);
+                assert_eq!(current.is_some(), level < list.height(), "level {} against height {}", level, list.height());
+                let mut linked = 0;
+                while let Some(node) = current {
+                    linked += 1;
+                    current = (*node.as_ptr()).next(level);
+                    if let Some(next_node) = current {
+                        assert!((*node.as_ptr()).key < (*next_node.as_ptr()).key, "level {} is not sorted", level);
+                    }
+                }
+                assert_eq!(linked, heights.iter().filter(|height| **height > level).count(), "level {} is missing nodes", level);
+            }
+        }
+        assert_eq!(list.iter().count(), list.len());
+    }
+
+    // Implements Ord and Default but neither Display nor Debug.
+    #[derive(Default, PartialEq, Eq, PartialOrd, Ord)]
+    struct OpaqueKey {
+        id: u64,
+    }
+
+    #[test]
+    fn test_keys_below_default() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in -500..0 {
+            list.insert(_i * 2);
+        }
+        list.insert(0); // Equal to the head's key.
+        assert_eq!(list.min(), Some(&-1000));
+        assert_eq!(list.max(), Some(&0));
+        assert!(list.iter().copied().eq((-500..=0).map(|key| key * 2)));
+        assert!(list.iter().rev().copied().eq((-500..=0).rev().map(|key| key * 2)));
+        assert!(list.contains(&-1000) && list.contains(&-2) && list.contains(&0));
+        assert!(!list.contains(&-1001) && !list.contains(&-1) && !list.contains(&1));
+        assert_eq!(list.closest(&-1001), Some((&-1000, Ordering::Greater)));
+        assert_eq!(list.get_bound(Bound::Excluded(&-1000), Direction::Backward), None);
+        assert_eq!(list.get_bound(Bound::Included(&-1), Direction::Backward), Some(&-2));
+
+        let mut cursor = list.cursor();
+        cursor.seek(&-1000).unwrap();
+        assert_eq!(cursor.prev(), Ok(()));
+        assert!(!cursor.valid());
+        cursor.seek_for_prev(&-999).unwrap();
+        assert_eq!(cursor.key(), Some(&-1000));
+
+        assert!(list.remove(&-1000));
+        assert!(list.remove(&0));
+        assert_eq!(list.min(), Some(&-998));
+        assert_eq!(list.max(), Some(&-2));
+        assert_invariants(&list);
+    }
+
+    // Its default is greater than every other value, so the head's key sorts after every real key.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+    struct DefaultsHigh(u32);
+
+    impl Default for DefaultsHigh {
+        fn default() -> Self {
+            DefaultsHigh(u32::MAX)
+        }
+    }
+
+    #[test]
+    fn test_head_key_above_every_key() {
+        let mut list: LinkedListSkipList<DefaultsHigh, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in (0..300).rev() {
+            list.insert(DefaultsHigh(_i));
+        }
+        assert_eq!(list.min(), Some(&DefaultsHigh(0)));
+        assert_eq!(list.max(), Some(&DefaultsHigh(299)));
+        assert!(list.iter().map(|key| key.0).eq(0..300));
+        assert!((0..300).all(|key| list.contains(&DefaultsHigh(key))));
+        assert!(!list.contains(&DefaultsHigh(u32::MAX)));
+        assert_eq!(list.get(&DefaultsHigh(7)), Some(&DefaultsHigh(7)));
+        assert!(list.remove(&DefaultsHigh(0)));
+        assert_eq!(list.min(), Some(&DefaultsHigh(1)));
+        assert_invariants(&list);
+    }
+
+    #[test]
+    fn test_key_without_display() {
+        let mut list: LinkedListSkipList<OpaqueKey, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for id in (0..100).rev() {
+            list.insert(OpaqueKey { id });
+        }
+        assert!(list.contains(&OpaqueKey { id: 50 }));
+        assert!(list.remove(&OpaqueKey { id: 50 }));
+        assert!(!list.contains(&OpaqueKey { id: 50 }));
+        assert!(list.iter().map(|key| key.id).eq((0..100).filter(|id| *id != 50)));
+
+        let mut iter = list.into_iter();
+        iter.seek(&OpaqueKey { id: 10 }).unwrap();
+        assert_eq!(iter.key().unwrap().id, 10);
+    }
+
+    #[test]
+    fn test_debug() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for key in [3, 1, 2] {
+            list.insert(key);
+        }
+        assert_eq!(format!("{:?}", list), "{1, 2, 3}");
+    }
+
+    #[test]
+    fn test_iter_rev() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert_eq!(list.iter().next_back(), None);
+        for _i in 0..500 {
+            list.insert(fastrand::i32(0..1000));
+        }
+        let forward: Vec<&i32> = list.iter().collect();
+        let mut backward: Vec<&i32> = list.iter().rev().collect();
+        backward.reverse();
+        assert_eq!(forward, backward);
+    }
+
+    #[test]
+    fn test_iter_interleaved_next_back() {
+        seed_test_rng();
+        for size in [0, 1, 2, 101, 200] {
+            let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+            for _i in 0..size {
+                list.insert(_i);
+            }
+            let mut iter = list.iter();
+            let mut seen = Vec::new();
+            loop {
+                let key = if fastrand::bool() { iter.next() } else { iter.next_back() };
+                match key {
+                    Some(key) => seen.push(*key),
+                    None => break,
+                }
+            }
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.next_back(), None);
+            seen.sort();
+            assert_eq!(seen, (0..size).collect::<Vec<i32>>());
+        }
+    }
+
+    #[test]
+    fn test_min_max() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert_eq!(list.min(), None);
+        assert_eq!(list.max(), None);
+        let mut keys: Vec<i32> = (0..1000).collect();
+        fastrand::shuffle(&mut keys);
+        for key in keys {
+            list.insert(key);
+        }
+        assert_eq!(list.min(), Some(&0));
+        assert_eq!(list.max(), Some(&999));
+
+        list.remove(&0);
+        list.remove(&999);
+        assert_eq!(list.min(), Some(&1));
+        assert_eq!(list.max(), Some(&998));
+        for key in 1..999 {
+            list.remove(&key);
+        }
+        assert_eq!(list.min(), None);
+        assert_eq!(list.max(), None);
+    }
+
+    #[test]
+    fn test_cursor_clone() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..100 {
+            list.insert(_i);
+        }
+        let mut cursor = list.cursor();
+        cursor.seek(&40).unwrap();
+        let mut fork = cursor.clone();
+        fork.skip_forward(10);
+        assert_eq!(fork.key(), Some(&50));
+        assert_eq!(cursor.key(), Some(&40));
+
+        assert_eq!(cursor.next(), Some(&41));
+        assert_eq!(fork.next(), Some(&51));
+        assert!(cursor.by_ref().take(5).copied().eq(42..47));
+        assert!(fork.copied().eq(52..100));
+        assert_eq!(cursor.next(), Some(&47));
+    }
+
+    #[test]
+    fn test_cursor_from() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..50 {
+            list.insert(_i * 2);
+        }
+        assert_eq!(list.cursor_from(Bound::Included(&20)).next(), Some(&20));
+        assert_eq!(list.cursor_from(Bound::Included(&21)).next(), Some(&22));
+        assert_eq!(list.cursor_from(Bound::Excluded(&20)).next(), Some(&22));
+        assert_eq!(list.cursor_from(Bound::Excluded(&21)).next(), Some(&22));
+        assert_eq!(list.cursor_from(Bound::Unbounded).next(), Some(&0));
+        assert_eq!(list.cursor_from(Bound::Included(&-5)).next(), Some(&0));
+        assert!(list.cursor_from(Bound::Excluded(&90)).copied().eq([92, 94, 96, 98]));
+        assert_eq!(list.cursor_from(Bound::Included(&99)).next(), None);
+        assert_eq!(list.cursor_from(Bound::Excluded(&98)).next(), None);
+    }
+
+    // Clock which only moves when told to.
+    #[derive(Default)]
+    struct MockClock(std::sync::atomic::AtomicU64);
+
+    impl MockClock {
+        fn set(&self, now: u64) {
+            self.0.store(now, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    impl Clock for std::sync::Arc<MockClock> {
+        fn now(&self) -> u64 {
+            self.0.load(std::sync::atomic::Ordering::Relaxed)
+        }
+    }
+
+    #[test]
+    fn test_ttl_expiry() {
+        let clock = std::sync::Arc::new(MockClock::default());
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new()
+            .with_clock(Box::new(clock.clone()));
+        for _i in 0..100 {
+            list.insert(_i);
+        }
+        assert_eq!(list.insert_with_ttl(100, 10), None);
+        assert_eq!(list.insert_with_ttl(50, 20), Some(50));
+        assert!(list.contains(&100));
+        assert_eq!(list.access(&100), Some(&100));
+
+        clock.set(10);
+        assert!(!list.contains(&100));
+        assert!(list.contains(&50));
+        assert_eq!(list.len(), 101);
+        assert_eq!(list.access(&100), None);
+        assert_eq!(list.len(), 100);
+        assert!(!list.iter().any(|key| *key == 100));
+
+        clock.set(20);
+        assert!(!list.contains(&50));
+        assert_eq!(list.insert_with_ttl(50, 5), None); // Overwriting an expired key replaces nothing.
+        assert!(list.contains(&50));
+        clock.set(25);
+        assert_eq!(list.access(&50), None);
+        assert!(list.iter().copied().eq((0..100).filter(|key| *key != 50)));
+        assert_spans(&list);
+    }
+
+    #[test]
+    fn test_iter_visible() {
+        // Keys are (id, seq, deleted).
+        let clock = std::sync::Arc::new(MockClock::default());
+        let mut list: LinkedListSkipList<(u32, u64, bool), { 2_usize.pow(6) }> = LinkedListSkipList::new()
+            .with_clock(Box::new(clock.clone()));
+        for id in 0..40 {
+            let seq = id as u64 % 8;
+            let deleted = id % 5 == 0;
+            if id % 3 == 0 {
+                list.insert_with_ttl((id, seq, deleted), 10 + id as u64 % 4);
+            } else {
+                list.insert((id, seq, deleted));
+            }
+        }
+        let visible = |snapshot_seq: u64, now: u64| -> Vec<u32> {
+            list.iter_visible(snapshot_seq, now, |key| key.1, |key| key.2).map(|key| key.0).collect()
+        };
+        let expected = |snapshot_seq: u64, now: u64| -> Vec<u32> {
+            (0..40)
+                .filter(|id| id % 3 != 0 || 10 + *id as u64 % 4 > now)
+                .filter(|id| *id as u64 % 8 <= snapshot_seq)
+                .filter(|id| id % 5 != 0)
+                .collect()
+        };
+        for (snapshot_seq, now) in [(u64::MAX, 0), (3, 0), (7, 11), (2, 12), (5, 100), (0, 100)] {
+            assert_eq!(visible(snapshot_seq, now), expected(snapshot_seq, now), "snapshot {} at {}", snapshot_seq, now);
+        }
+        // Every exclusion at once, 18 has expired, 13 is above the snapshot and 10 is deleted.
+        assert_eq!(visible(4, 12), vec![1, 2, 3, 4, 8, 11, 16, 17, 19, 26, 27, 28, 32, 34]);
+        // The list's own clock is not consulted.
+        clock.set(1000);
+        assert_eq!(visible(u64::MAX, 0), expected(u64::MAX, 0));
+        assert_eq!(list.len(), 40);
+    }
+
+    #[test]
+    fn test_total_memory() {
+        let node_bytes = Layout::new::<Node<i32>>().size();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let empty = list.total_memory();
+        assert!(empty >= list.memory_usage() + 2_usize.pow(6) * std::mem::size_of::<Link<i32>>());
+        for _i in 0..1000 {
+            list.insert(_i);
+        }
+        let full = list.total_memory();
+        assert!(full >= list.memory_usage());
+        // Every key adds a node and at least one link and span.
+        assert!(full - empty >= 1000 * (node_bytes + std::mem::size_of::<Link<i32>>() + std::mem::size_of::<usize>()));
+
+        // Removed nodes kept for reuse still count towards the total but not the usage.
+        for _cycle in 0..5 {
+            for _i in 0..1000 {
+                assert!(list.remove(&_i));
+            }
+            for _i in 0..1000 {
+                list.insert(_i);
+            }
+        }
+        for _i in 0..500 {
+            assert!(list.remove(&_i));
+        }
+        let kept: usize = list.free_nodes.iter().map(Vec::len).sum();
+        assert!(kept > 0);
+        assert_eq!(list.memory_usage(), 501 * node_bytes);
+        assert!(list.total_memory() - list.memory_usage() >= kept * node_bytes);
+        assert!(list.total_memory() < full + kept * node_bytes * 2);
+
+        // An arena is counted by the chunks it holds, which outlast removals.
+        let mut arena_list: LinkedListSkipList<i32, { 2_usize.pow(6) }, Arena> = LinkedListSkipList::new_in(Arena::new());
+        for _i in 0..100 {
+            arena_list.insert(_i);
+        }
+        let chunk_bytes = arena_list.alloc.reserved_bytes().unwrap();
+        assert!(chunk_bytes >= 64 * 1024);
+        assert!(arena_list.total_memory() >= chunk_bytes);
+        for _i in 0..100 {
+            assert!(arena_list.remove(&_i));
+        }
+        assert!(arena_list.total_memory() >= chunk_bytes);
+        assert_eq!(arena_list.memory_usage(), node_bytes);
+    }
+
+    #[test]
+    fn test_ttl_memory_reclaimed() {
+        let clock = std::sync::Arc::new(MockClock::default());
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new()
+            .with_clock(Box::new(clock.clone()));
+        let node_bytes = Layout::new::<Node<i32>>().size();
+        for _i in 0..10 {
+            list.insert(_i);
+        }
+        for _i in 10..30 {
+            list.insert_with_ttl(_i, 5 + _i as u64 % 2 * 10);
+        }
+        assert_eq!(list.len(), 30);
+        assert_eq!(list.memory_usage(), 31 * node_bytes);
+
+        // Expired keys are still counted until a read reclaims them.
+        clock.set(5);
+        assert_eq!(list.len(), 30);
+        for key in 0..30 {
+            assert_eq!(list.access(&key).is_some(), key < 10 || key % 2 == 1);
+        }
+        assert_eq!(list.len(), 20);
+        assert_eq!(list.memory_usage(), 21 * node_bytes);
+
+        clock.set(15);
+        for key in (11..30).step_by(2) {
+            assert_eq!(list.access(&key), None);
+        }
+        assert_eq!(list.len(), 10);
+        assert_eq!(list.memory_usage(), 11 * node_bytes);
+        assert_eq!(list.access(&11), None);
+        assert_eq!(list.len(), 10);
+        assert!(list.iter().copied().eq(0..10));
+        assert_invariants(&list);
+
+        // Reclaimed nodes are reused without counting them twice.
+        list.insert_with_ttl(11, 10);
+        assert_eq!(list.memory_usage(), 12 * node_bytes);
+        assert_invariants(&list);
+    }
+
+    #[test]
+    fn test_insert_ascending_unchecked() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..500 {
+            unsafe { list.insert_ascending_unchecked(_i * 2) };
+        }
+        assert_eq!(list.len(), 500);
+        assert!(list.iter().copied().eq((0..500).map(|key| key * 2)));
+        assert_spans(&list);
+
+        // Other writes invalidate the cached tails, appends after them must still land at the end.
+        list.insert(1000);
+        list.insert(1001);
+        list.remove(&998);
+        for _i in 0..500 {
+            list.insert(_i * 2 + 1);
+        }
+        for _i in 1002..1500 {
+            unsafe { list.insert_ascending_unchecked(_i) };
+        }
+        assert!(list.iter().copied().eq((0..998).chain(999..1500)));
+        assert_eq!(list.max(), Some(&1499));
+        assert_spans(&list);
+        for _i in (0..1500).filter(|key| *key != 998) {
+            assert!(list.contains(&_i));
+        }
+    }
+
+    #[test]
+    fn test_from_btree_set() {
+        seed_test_rng();
+        let set: BTreeSet<i32> = (0..5000).map(|_| fastrand::i32(-100_000..100_000)).collect();
+        let list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::from(set.clone());
+        assert_invariants(&list);
+        assert_spans(&list);
+        assert_eq!(list.len(), set.len());
+        assert!(list.iter().eq(set.iter()));
+        assert!(set.iter().all(|key| list.contains(key)));
+
+        let empty: LinkedListSkipList<i32, { 2_usize.pow(6) }> = BTreeSet::new().into();
+        assert!(empty.is_empty());
+        assert_invariants(&empty);
+    }
+
+    #[test]
+    fn test_reused_path_reset() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new().with_append_hint(false);
+        let assert_reset = |list: &LinkedListSkipList<i32, { 2_usize.pow(6) }>| {
+            let (previous, ranks) = &**list.path.as_ref().unwrap();
+            assert!(previous.iter().all(|node| list.is_head(*node)));
+            assert!(ranks.iter().all(|rank| *rank == 0));
+        };
+        for _i in 0..1000 {
+            list.insert(_i);
+        }
+        assert_reset(&list);
+        // Removals shrink the list below the levels earlier searches filled.
+        for _i in 0..995 {
+            assert!(list.remove(&_i));
+            assert_reset(&list);
+        }
+        assert!(list.insert_with_priority(2000, 10).is_none());
+        assert_eq!(list.access(&2000), Some(&2000));
+        assert_reset(&list);
+        assert_invariants(&list);
+        assert!(list.iter().copied().eq((995..1000).chain([2000])));
+    }
+
+    #[test]
+    fn test_append_hint() {
+        seed_test_rng();
+        let sink = std::sync::Arc::new(TotalingSink::default());
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new().with_metrics(Box::new(sink.clone()));
+        for _i in 0..1000 {
+            list.insert(_i * 3);
+        }
+        // The first insert found the list empty and every later one followed the tails.
+        assert_eq!(sink.searches.load(std::sync::atomic::Ordering::Relaxed), 0);
+        assert_spans(&list);
+
+        // Out of order keys fall back to a search and drop the hint, which the next insert at the
+        // end picks up again.
+        let mut expected: Vec<i32> = (0..1000).map(|key| key * 3).collect();
+        let mut next = 3000;
+        for _i in 0..2000 {
+            let key = if fastrand::u8(..4) == 0 {
+                fastrand::i32(..next)
+            } else {
+                next += fastrand::i32(1..4);
+                next
+            };
+            if !expected.contains(&key) {
+                expected.push(key);
+            }
+            list.insert_with_height(key, 1 + fastrand::usize(..8));
+            if fastrand::u8(..16) == 0 {
+                let removed = expected.swap_remove(fastrand::usize(..expected.len()));
+                assert!(list.remove(&removed));
+            }
+        }
+        expected.sort();
+        assert!(list.iter().eq(expected.iter()));
+        assert_invariants(&list);
+        for key in &expected {
+            assert!(list.contains(key));
+        }
+
+        let sink = std::sync::Arc::new(TotalingSink::default());
+        let mut unhinted: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new()
+            .with_metrics(Box::new(sink.clone()))
+            .with_append_hint(false);
+        for _i in 0..1000 {
+            unhinted.insert(_i);
+        }
+        assert_eq!(sink.searches.load(std::sync::atomic::Ordering::Relaxed), 999);
+        assert!(unhinted.iter().copied().eq(0..1000));
+    }
+
+    #[test]
+    fn test_node_ref_walk() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..500 {
+            list.insert(fastrand::i32(0..10_000));
+        }
+        let mut cursor = list.cursor();
+        cursor.seek(&2500).unwrap();
+        let mut expected = vec![*cursor.key().unwrap()];
+        expected.extend(cursor.as_std_iter().copied());
+        unsafe {
+            let mut walked = Vec::new();
+            let mut node = list.seek_node(&2500);
+            while let Some(current) = node {
+                assert!((1..=2_usize.pow(6)).contains(&current.height()));
+                walked.push(*current.key());
+                node = current.next();
+            }
+            assert_eq!(walked, expected);
+
+            let min = list.min_node().unwrap();
+            assert_eq!(min.key(), list.min().unwrap());
+            assert_eq!(list.seek_node(list.min().unwrap()), Some(min));
+            let max = list.max_node().unwrap();
+            assert_eq!(max.key(), list.max().unwrap());
+            assert_eq!(max.next(), None);
+            let mut tallest = 0;
+            let mut node = Some(min);
+            while let Some(current) = node {
+                tallest = tallest.max(current.height());
+                node = current.next();
+            }
+            assert_eq!(tallest, list.get_max_height());
+        }
+        assert!(list.seek_node(&10_000).is_none());
+        let empty: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert!(empty.min_node().is_none() && empty.max_node().is_none() && empty.seek_node(&0).is_none());
+    }
+
+    #[test]
+    fn test_range_owned() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(format!("{:04}", fastrand::u32(..5000)));
+        }
+        for (start, end) in [("0000", "9999"), ("1000", "2000"), ("1234", "1235"), ("3000", "1000"), ("5000", "6000"), ("", "0500")] {
+            let (start, end) = (start.to_string(), end.to_string());
+            let borrowed: Vec<String> = list.view().range(&start..&end).cloned().collect();
+            let owned = list.range_owned(&start, &end);
+            assert_eq!(owned, borrowed, "[{}, {})", start, end);
+            assert_eq!(owned.capacity(), owned.len());
+        }
+
+        let owned = list.range_owned(&"2000".to_string(), &"4000".to_string());
+        list.insert("3000".to_string());
+        let expected = owned.clone();
+        let handed_off = std::thread::spawn(move || owned).join().unwrap();
+        assert_eq!(handed_off, expected);
+        assert!(handed_off.iter().all(|key| key.as_str() >= "2000" && key.as_str() < "4000"));
+
+        let empty: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert!(empty.range_owned(&String::new(), &"z".to_string()).is_empty());
+    }
+
+    #[test]
+    fn test_split_off() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(_i);
+        }
+        let upper = list.split_off(&500);
+        assert!(list.iter().copied().eq(0..500));
+        assert!(upper.iter().copied().eq(500..1000));
+        assert_eq!(list.len(), 500);
+        assert_eq!(upper.len(), 500);
+        assert_invariants(&list);
+        assert_invariants(&upper);
+        assert!(list.contains(&499) && !list.contains(&500));
+        assert!(upper.contains(&500) && !upper.contains(&499));
+
+        let mut empty = list.split_off(&1000);
+        assert!(empty.is_empty());
+        assert_invariants(&empty);
+        empty.insert(5);
+        assert_invariants(&empty);
+        let all = list.split_off(&-1);
+        assert!(list.is_empty());
+        assert_invariants(&list);
+        assert!(all.iter().copied().eq(0..500));
+        assert_invariants(&all);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut other: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut expected: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..500 {
+            list.insert(_i);
+            other.insert(_i + 500);
+        }
+        for _i in 0..1000 {
+            expected.insert(_i);
+        }
+        list.append(other);
+        assert_invariants(&list);
+        assert_eq!(list.len(), expected.len());
+        assert!(list.iter().eq(expected.iter()));
+        for _i in 0..1000 {
+            assert!(list.contains(&_i));
+        }
+        list.insert(1000);
+        assert!(list.remove(&500));
+        assert_invariants(&list);
+
+        let mut empty: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        empty.append(list);
+        assert_invariants(&empty);
+        empty.append(LinkedListSkipList::new());
+        assert_invariants(&empty);
+        assert!(empty.iter().copied().eq((0..500).chain(501..1001)));
     }
 
     #[test]
-    fn test_into_iterator() {
+    fn test_with_capacity() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }, Arena> = LinkedListSkipList::with_capacity(100_000);
+        for _i in 0..100_000 {
+            list.insert(_i);
+        }
+        assert_eq!(list.alloc.chunks(), 1);
+        assert_eq!(list.len(), 100_000);
+
+        let mut unreserved: LinkedListSkipList<i32, { 2_usize.pow(6) }, Arena> = LinkedListSkipList::new_in(Arena::new());
+        for _i in 0..100_000 {
+            unreserved.insert(_i);
+        }
+        assert!(unreserved.alloc.chunks() > 1);
+    }
+
+    #[test]
+    fn test_arena_append() {
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }, Arena> = LinkedListSkipList::with_capacity(10);
+        let mut other: LinkedListSkipList<String, { 2_usize.pow(6) }, Arena> = LinkedListSkipList::new_in(Arena::new());
+        for _i in 0..100 {
+            list.insert(format!("{:03}", _i));
+            other.insert(format!("{:03}", _i + 100));
+        }
+        list.append(other);
+        assert!(list.remove(&"150".to_string()));
+        list.insert("200".to_string());
+        assert_eq!(list.len(), 200);
+        let expected: Vec<String> = (0..201).filter(|key| *key != 150).map(|key| format!("{:03}", key)).collect();
+        assert!(list.iter().eq(expected.iter()));
+    }
+
+    #[test]
+    fn test_iterator_exhausted() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..10 {
+            list.insert(_i);
+        }
+        let mut cursor = list.cursor();
+        assert!(cursor.by_ref().copied().eq(0..10));
+        assert!(!cursor.valid());
+        assert_eq!(cursor.next(), None);
+
+        cursor.seek(&9).unwrap();
+        assert!(cursor.valid());
+        cursor.advance();
+        assert!(!cursor.valid());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "key called on an invalid iterator")]
+    fn test_iterator_key_when_exhausted() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..10 {
+            list.insert(_i);
+        }
+        let mut iter = list.into_iter();
+        for _ in iter.by_ref() {}
+        iter.key();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "key called on an invalid iterator")]
+    fn test_cursor_key_when_exhausted() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        list.insert(1);
+        let mut cursor = list.cursor();
+        for _ in cursor.by_ref() {}
+        cursor.key();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "key called on an iterator before the first key")]
+    fn test_cursor_key_after_reset() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        list.insert(1);
+        let mut cursor = list.cursor();
+        cursor.reset();
+        cursor.key();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "advance called on an invalid iterator")]
+    fn test_iterator_advance_when_exhausted() {
+        let list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut cursor = list.cursor();
+        cursor.advance();
+        cursor.advance();
+    }
+
+    #[test]
+    fn test_height_cap() {
+        type List = LinkedListSkipList<'static, i32, { 2_usize.pow(6) }>;
+        assert_eq!(List::height_cap(1), 2);
+        assert_eq!(List::height_cap(10), 6);
+        assert_eq!(List::height_cap(10_000), 16);
+        assert_eq!(List::height_cap(10_000_000), 26);
+        assert_eq!(List::height_cap(usize::MAX), 64);
+        assert_eq!(LinkedListSkipList::<'static, i32, 4>::height_cap(10_000), 4);
+
+        for size in [10, 10_000] {
+            let mut list: List = LinkedListSkipList::new();
+            for _i in 0..size {
+                unsafe { list.insert_ascending_unchecked(_i) };
+            }
+            let log2 = (size as f64).log2();
+            assert!(list.get_max_height() <= List::height_cap(size as usize));
+            assert!(list.get_max_height() as f64 >= log2 - 4.0, "height {} for {} keys", list.get_max_height(), size);
+        }
+    }
+
+    #[test]
+    fn test_fold() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert_eq!(list.fold(0, |sum, key| sum + key), 0);
+        for _i in (0..1000).rev() {
+            list.insert(_i);
+        }
+        assert_eq!(list.fold(0, |sum, key| sum + key), 499500);
+        assert_eq!(list.fold(Vec::new(), |mut keys, key| { keys.push(*key); keys }), (0..1000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_scan_while() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(_i * 3);
+        }
+        // The first multiple of 7 from 100, returned as its quotient.
+        let found = list.scan_while(&100, |key| if key % 7 == 0 { ControlFlow::Break(key / 7) } else { ControlFlow::Continue(()) });
+        assert_eq!(found, Some(15));
+
+        let mut visited = 0;
+        let found = list.scan_while(&2990, |key| {
+            visited += 1;
+            if *key > 5000 { ControlFlow::Break(*key) } else { ControlFlow::Continue(()) }
+        });
+        assert_eq!(found, None);
+        assert_eq!(visited, 3);
+        assert_eq!(list.scan_while(&-5, |key| ControlFlow::Break(*key)), Some(0));
+        assert_eq!(list.scan_while(&3000, |key| ControlFlow::Break(*key)), None);
+    }
+
+    #[test]
+    fn test_prefetch() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        list.prefetch();
+        for _i in 0..1000 {
+            list.insert(_i);
+        }
+        let before = list.dump();
+        list.prefetch();
+        assert_eq!(list.dump(), before);
+        assert!((0..1000).all(|key| list.contains(&key)));
+    }
+
+    #[test]
+    fn test_get_bound() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert_eq!(list.get_bound(Bound::Unbounded, Direction::Forward), None);
+        assert_eq!(list.get_bound(Bound::Unbounded, Direction::Backward), None);
+        for key in [10, 20, 40, 80, 160] {
+            list.insert(key);
+        }
+        let forward = |bound| list.get_bound(bound, Direction::Forward).copied();
+        let backward = |bound| list.get_bound(bound, Direction::Backward).copied();
+
+        assert_eq!(forward(Bound::Included(&40)), Some(40));
+        assert_eq!(forward(Bound::Included(&41)), Some(80));
+        assert_eq!(forward(Bound::Excluded(&40)), Some(80));
+        assert_eq!(forward(Bound::Excluded(&5)), Some(10));
+        assert_eq!(forward(Bound::Included(&161)), None);
+        assert_eq!(forward(Bound::Excluded(&160)), None);
+        assert_eq!(forward(Bound::Unbounded), Some(10));
+
+        assert_eq!(backward(Bound::Included(&40)), Some(40));
+        assert_eq!(backward(Bound::Included(&39)), Some(20));
+        assert_eq!(backward(Bound::Excluded(&40)), Some(20));
+        assert_eq!(backward(Bound::Excluded(&500)), Some(160));
+        assert_eq!(backward(Bound::Included(&9)), None);
+        assert_eq!(backward(Bound::Excluded(&10)), None);
+        assert_eq!(backward(Bound::Unbounded), Some(160));
+    }
+
+    #[test]
+    fn test_fold_max() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(_i);
+        }
+        // The largest remainder is not at the largest key.
+        let max_remainder = list.fold(None, |max: Option<i32>, key| max.max(Some(key % 97)));
+        assert_eq!(max_remainder, Some(96));
+    }
+
+    #[test]
+    fn test_search_after_tall_tower_removed() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(_i);
+        }
+        // Remove the tallest towers, leaving the upper levels of the head empty.
+        for _ in 0..3 {
+            let tallest = unsafe { (*list.head_next(list.get_max_height() - 1).unwrap().as_ptr()).key };
+            assert!(list.remove(&tallest));
+        }
+        assert_invariants(&list);
+        let remaining: Vec<i32> = list.iter().copied().collect();
+
+        // Searches must also survive a height left above the tallest remaining tower.
+        list.grow_head(2_usize.pow(6));
+        *list.current_height.get_mut() = 2_usize.pow(6);
+        for _i in -1..1001 {
+            assert_eq!(list.contains(&_i), remaining.contains(&_i));
+        }
+        let mut cursor = list.cursor();
+        cursor.seek(&500).unwrap();
+        assert_eq!(cursor.key(), remaining.iter().find(|key| **key >= 500));
+        list.insert(1000);
+        assert!(list.remove(&remaining[0]));
+        assert!(list.iter().eq(remaining[1..].iter().chain([1000].iter())));
+        assert_eq!(list.get_max_height(), unsafe { (0..64).rev().find(|level| list.head_next(*level).is_some()).unwrap() + 1 });
+    }
+
+    #[test]
+    fn test_comparison_cache() {
+        let mut naive: LinkedListSkipList<CountedKey, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut cached: LinkedListSkipList<CountedKey, { 2_usize.pow(6) }> = LinkedListSkipList::new().with_comparison_cache(true);
+        for _i in 0..10_000 {
+            naive.insert(CountedKey(_i * 2));
+            cached.insert(CountedKey(_i * 2));
+        }
+        let lookups: Vec<CountedKey> = (-1..20_001).step_by(3).map(CountedKey).collect();
+        for key in &lookups {
+            assert_eq!(cached.contains(key), naive.contains(key));
+            let mut cursor = cached.cursor();
+            assert_eq!(cursor.seek(key).is_ok(), key.0 <= 19_998);
+            if key.0 <= 19_998 {
+                assert_eq!(cursor.key().unwrap().0, key.0 + key.0.rem_euclid(2));
+            }
+        }
+        let naive_comparisons = count_comparisons(|| lookups.iter().for_each(|key| { naive.contains(key); }));
+        let cached_comparisons = count_comparisons(|| lookups.iter().for_each(|key| { cached.contains(key); }));
+        assert!(cached_comparisons < naive_comparisons, "{} cached against {} naive", cached_comparisons, naive_comparisons);
+    }
+
+    #[test]
+    fn test_balance_factor() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert_eq!(list.balance_factor(), 1.0);
+        for _i in 0..10_000 {
+            list.insert(fastrand::i32(..));
+        }
+        let factor = list.balance_factor();
+        assert!((0.75..1.5).contains(&factor), "balance factor {}", factor);
+
+        let mut flat: LinkedListSkipList<i32, 1> = LinkedListSkipList::new();
+        for _i in 0..10_000 {
+            unsafe { flat.insert_ascending_unchecked(_i) };
+        }
+        let factor = flat.balance_factor();
+        assert!(factor > 100.0, "balance factor {}", factor);
+    }
+
+    #[test]
+    fn test_split_points() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..10_000 {
+            list.insert(fastrand::i32(..));
+        }
+        let len = list.len();
+        let view = list.view();
+        for n in [1, 2, 3, 7, 64] {
+            let split = list.split_points(n);
+            assert_eq!(split.len(), n - 1);
+            let bounds: Vec<(Bound<&i32>, Bound<&i32>)> = (0..n)
+                .map(|shard| {
+                    let start = if shard == 0 { Bound::Unbounded } else { Bound::Included(split[shard - 1]) };
+                    let end = if shard == n - 1 { Bound::Unbounded } else { Bound::Excluded(split[shard]) };
+                    (start, end)
+                })
+                .collect();
+            let shards: Vec<Vec<i32>> = bounds.into_iter().map(|range| view.range(range).copied().collect()).collect();
+            assert!(shards.iter().all(|shard| shard.len() == len / n || shard.len() == len / n + 1), "{:?}", shards.iter().map(Vec::len).collect::<Vec<_>>());
+            assert!(shards.concat().iter().eq(list.iter()));
+        }
+
+        let mut small: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert!(small.split_points(4).is_empty());
+        for key in [10, 20, 30] {
+            small.insert(key);
+        }
+        assert_eq!(small.split_points(8), vec![&20, &30]);
+        assert!(small.split_points(0).is_empty());
+        assert_eq!((1..=3).map(|rank| small.node_at_rank(rank).map(|node| unsafe { (*node.as_ptr()).key })).collect::<Vec<_>>(), [Some(10), Some(20), Some(30)]);
+        assert!(small.node_at_rank(4).is_none());
+    }
+
+    #[test]
+    fn test_head_shrinks_and_regrows() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let head_links = |list: &LinkedListSkipList<i32, { 2_usize.pow(6) }>| unsafe { (*list.head.as_ptr()).height() };
+        assert_eq!(head_links(&list), 2_usize.pow(6));
+        for _i in 0..100 {
+            list.insert_with_height(_i * 2, 1 + _i as usize % 3);
+        }
+        for key in [51, 53, 55] {
+            list.insert_with_height(key, 40);
+        }
+        assert_eq!(list.height(), 40);
+        assert_eq!(list.remove_sorted([51, 53, 55]), 3);
+        assert_eq!(list.height(), 3);
+        assert_eq!(head_links(&list), 3);
+        assert_invariants(&list);
+
+        // A taller tower grows the head back, to just the height it needs.
+        list.insert_with_height(77, 10);
+        assert_eq!(head_links(&list), 10);
+        assert_invariants(&list);
+        assert!(list.contains(&77) && list.contains(&198) && !list.contains(&51));
+        assert!(list.remove(&77));
+        list.rebuild();
+        assert_eq!(head_links(&list), list.height());
+        assert_invariants(&list);
+
+        // An emptied list keeps the bottom level.
+        for _i in 0..100 {
+            assert!(list.remove(&(_i * 2)));
+        }
+        assert_eq!(head_links(&list), 1);
+        assert_eq!(list.iter().next(), None);
+        list.insert(5);
+        assert!(list.iter().copied().eq([5]));
+        assert_invariants(&list);
+    }
+
+    #[test]
+    fn test_insert_with_priority() {
+        let sink = std::sync::Arc::new(TotalingSink::default());
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new().with_metrics(Box::new(sink.clone()));
+        let hot = [17, 4242, 9001];
+        for _i in 0..10_000 {
+            if hot.contains(&_i) {
+                assert_eq!(list.insert_with_priority(_i, 15), None);
+            } else {
+                list.insert(_i);
+            }
+        }
+        assert_eq!(list.insert_with_priority(10_000, 0), None);
+        assert_invariants(&list);
+        unsafe {
+            for key in hot {
+                assert!(list.seek_node(&key).unwrap().height() >= 16);
+            }
+        }
+
+        // A hot key is reached in no more comparisons than the list has levels, plus the drop to it.
+        let comparisons = |list: &LinkedListSkipList<i32, { 2_usize.pow(6) }>, key: i32| {
+            let before = sink.comparisons.load(std::sync::atomic::Ordering::Relaxed);
+            assert!(list.contains(&key));
+            sink.comparisons.load(std::sync::atomic::Ordering::Relaxed) - before
+        };
+        for key in hot {
+            assert!(comparisons(&list, key) <= 2 * list.get_max_height(), "key {}", key);
+        }
+
+        // Replacing a key keeps its node, and a priority past the top is clamped.
+        assert_eq!(list.insert_with_priority(17, 100), Some(17));
+        assert_eq!(list.insert_with_priority(-1, 100), None);
+        unsafe {
+            assert_eq!(list.seek_node(&-1).unwrap().height(), 2_usize.pow(6));
+        }
+        assert_invariants(&list);
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..5000 {
+            list.insert(_i * 2);
+        }
+        let height = list.get_max_height();
+        let mut list = Some(list);
+        for index_levels in [0, 1, 4, height - 1, height, height + 10] {
+            let frozen = list.take().unwrap().freeze(index_levels);
+            assert_eq!(frozen.len(), 5000);
+            match index_levels {
+                0 => assert_eq!(frozen.index_len(), 0),
+                _ if index_levels >= height => assert_eq!(frozen.index_len(), 5000),
+                _ => assert!(frozen.index_len() > 0 && frozen.index_len() < 5000),
+            }
+            for key in -1..10_001 {
+                assert_eq!(frozen.contains(&key), (0..10_000).contains(&key) && key % 2 == 0, "key {} packing {} levels", key, index_levels);
+            }
+            assert_eq!(frozen.get(&4242), Some(&4242));
+            assert!(frozen.iter().copied().eq((0..5000).map(|key| key * 2)));
+            list = Some(frozen.thaw());
+        }
+        let mut list = list.unwrap();
+        list.insert(1);
+        assert_invariants(&list);
+
+        let frozen = list.freeze(3);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| assert!((0..5000).all(|key| frozen.contains(&(key * 2)))));
+            }
+        });
+        let empty: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let empty = empty.freeze(4);
+        assert!(empty.is_empty() && !empty.contains(&0) && empty.index_len() == 0);
+    }
+
+    #[test]
+    fn test_rebuild() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..10_000 {
+            list.insert_with_height(_i * 3, 1);
+        }
+        let degenerate = list.balance_factor();
+        assert!(degenerate > 100.0, "balance factor {}", degenerate);
+
+        list.rebuild();
+        let factor = list.balance_factor();
+        assert!((0.75..1.5).contains(&factor), "balance factor {}", factor);
+        assert!(list.get_max_height() > 1);
+        assert_invariants(&list);
+        assert_eq!(list.len(), 10_000);
+        assert!(list.iter().copied().eq((0..10_000).map(|key| key * 3)));
+        assert!(list.contains(&2_997) && !list.contains(&2_998));
+
+        // Writes after a rebuild see the new towers.
+        list.insert(1);
+        assert!(list.remove(&3));
+        assert_invariants(&list);
+
+        let mut empty: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        empty.rebuild();
+        assert!(empty.is_empty());
+        assert_invariants(&empty);
+    }
+
+    #[test]
+    fn test_read_repair() {
+        use std::sync::atomic::Ordering::Relaxed;
+        let sink = std::sync::Arc::new(TotalingSink::default());
+        let mut list: LinkedListSkipList<i32, 8> = LinkedListSkipList::new().with_read_repair(true).with_metrics(Box::new(sink.clone()));
+        for key in 0..20 {
+            list.insert_with_height(key, if key % 4 == 0 { 2 } else { 1 });
+        }
+        assert_invariants(&list);
+        // Drop 8 from level 1 as if a splice had been cut short, leaving 4 linked straight to 12.
+        unsafe {
+            let four = list.find_first_not_before(|key| *key < 4).unwrap().as_ptr();
+            let eight = (*four).next(1).unwrap();
+            assert_eq!((*eight.as_ptr()).key, 8);
+            (*four).set_next(1, (*eight.as_ptr()).next(1));
+            (*four).set_span(1, 8);
+        }
+        // Lookups through a shared reference leave the list alone.
+        assert!(list.contains(&10));
+        assert_eq!(sink.repairs.load(Relaxed), 0);
+        assert!(unsafe { (*list.find_first_not_before(|key| *key < 4).unwrap().as_ptr()).next(1) }.is_some_and(|next| unsafe { (*next.as_ptr()).key } == 12));
+
+        assert_eq!(list.access(&10), Some(&10));
+        assert_eq!(sink.repairs.load(Relaxed), 1);
+        assert_invariants(&list);
+        assert_eq!(list.access(&10), Some(&10));
+        assert_eq!(sink.repairs.load(Relaxed), 1);
+        assert!(list.remove(&8));
+        assert_invariants(&list);
+    }
+
+    #[test]
+    fn test_insert_with_height() {
+        let mut list: LinkedListSkipList<i32, 8> = LinkedListSkipList::new();
+        let heights = [1, 3, 1, 2, 5, 1, 1, 2, 1, 4];
+        // Insert out of order so towers are spliced between existing ones.
+        for key in [4, 9, 0, 7, 2, 1, 8, 3, 6, 5] {
+            assert_eq!(list.insert_with_height(key, heights[key as usize]), None);
+            assert_invariants(&list);
+        }
+        assert_eq!(list.get_max_height(), 5);
+        for key in 0..10 {
+            assert_eq!(unsafe { (*list.find_first_not_before(|k| *k < key).unwrap().as_ptr()).height() }, heights[key as usize]);
+        }
+        for key in -1..11 {
+            assert_eq!(list.contains(&key), (0..10).contains(&key));
+        }
+        let mut cursor = list.cursor();
+        cursor.seek(&5).unwrap();
+        assert!(cursor.copied().eq(6..10));
+
+        // Heights are clamped, and an existing node keeps its height.
+        assert_eq!(list.insert_with_height(10, 100), None);
+        assert_eq!(list.insert_with_height(11, 0), None);
+        assert_eq!(list.get_max_height(), 8);
+        assert_eq!(list.insert_with_height(4, 1), Some(4));
+        assert_invariants(&list);
+
+        assert!(list.remove(&10));
+        assert_eq!(list.get_max_height(), 5);
+        assert!(list.remove(&4));
+        assert_eq!(list.get_max_height(), 4);
+        assert_invariants(&list);
+        assert!(list.iter().copied().eq((0..4).chain(5..10).chain([11])));
+    }
+
+    #[test]
+    fn test_remove_sorted() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(_i);
+        }
+        // Multiples of 3 from beyond both ends of the list, only 334 of which are present.
+        assert_eq!(list.remove_sorted((-30..1030).step_by(3)), 334);
+        assert_eq!(list.len(), 666);
+        assert_invariants(&list);
+        assert!(list.iter().copied().eq((0..1000).filter(|key| key % 3 != 0)));
+
+        // Removed and duplicate keys are skipped, out of order input still removes every key.
+        assert_eq!(list.remove_sorted([3, 1, 1, 2, 5, 4, 999]), 4);
+        assert_eq!(list.remove_sorted(Vec::new()), 0);
+        assert_invariants(&list);
+        assert!(list.iter().copied().eq((0..1000).filter(|key| key % 3 != 0 && ![1, 2, 4, 5].contains(key))));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "level 3 exceeds node height 3")]
+    fn test_set_next_past_height() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        list.insert_with_height(1, 3);
+        unsafe {
+            let node = list.head_next(0).unwrap();
+            (*node.as_ptr()).set_next(3, None);
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "level 5 exceeds node height 1")]
+    fn test_next_past_height() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        list.insert_with_height(1, 1);
+        unsafe {
+            let node = list.head_next(0).unwrap();
+            (*node.as_ptr()).next(5);
+        }
+    }
+
+    #[test]
+    fn test_memory_usage() {
         let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let empty = list.memory_usage();
         for _i in 0..100 {
             list.insert(_i);
         }
+        let full = list.memory_usage();
+        assert!(full > empty);
+        assert_eq!(list.replace(50), Some(50));
+        assert_eq!(list.memory_usage(), full);
+        list.remove(&50);
+        assert_eq!(list.memory_usage(), (full - empty) / 100 * 99 + empty);
+    }
 
-        let mut expected_val  = 0;
-        let iter = list.into_iter();
-        for _i in iter {
-            assert_eq!(&expected_val, _i);
-            expected_val += 1;
+    thread_local! {
+        // Comparisons of `FaultyKey`s left before one panics, None to never panic.
+        static COMPARISONS_UNTIL_PANIC: std::cell::Cell<Option<usize>> = const { std::cell::Cell::new(None) };
+    }
+
+    // Key whose comparison panics once a set number of comparisons have been made.
+    #[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+    struct FaultyKey(i32);
+
+    impl Ord for FaultyKey {
+        fn cmp(&self, other: &Self) -> Ordering {
+            COMPARISONS_UNTIL_PANIC.with(|remaining| match remaining.get() {
+                Some(0) => panic!("comparison failed"),
+                Some(left) => remaining.set(Some(left - 1)),
+                None => (),
+            });
+            self.0.cmp(&other.0)
+        }
+    }
+
+    impl PartialOrd for FaultyKey {
+        fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+            Some(self.cmp(other))
         }
     }
 
     #[test]
-    fn test_iterator_seek() {
+    fn test_panicking_comparison_leaves_list_consistent() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<FaultyKey, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..200 {
+            list.insert(FaultyKey(_i * 2));
+        }
+        let mut expected: Vec<i32> = list.iter().map(|key| key.0).collect();
+        // Panic at every point of inserting a key, through the search and into the splice.
+        for limit in 0..40 {
+            let key = fastrand::i32(0..200) * 2 + 1;
+            COMPARISONS_UNTIL_PANIC.with(|remaining| remaining.set(Some(limit)));
+            let inserted = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                list.insert_with_height(FaultyKey(key), fastrand::usize(1..8))
+            }));
+            COMPARISONS_UNTIL_PANIC.with(|remaining| remaining.set(None));
+            if inserted.is_ok() && !expected.contains(&key) {
+                expected.push(key);
+                expected.sort();
+            }
+            assert_invariants(&list);
+            assert!(list.iter().map(|key| key.0).eq(expected.iter().copied()));
+        }
+        assert!(expected.len() < 240, "every insert finished without panicking");
+    }
+
+    #[test]
+    fn test_concurrent_inserts() {
+        // Writes need `&mut`, so writers on several threads share the list through a lock.
+        let list: std::sync::Mutex<LinkedListSkipList<i32, { 2_usize.pow(6) }>> = Default::default();
+        let writers = 8;
+        let per_writer = 2_000;
+        std::thread::scope(|scope| {
+            for writer in 0..writers {
+                let list = &list;
+                scope.spawn(move || {
+                    for _i in writer * per_writer..(writer + 1) * per_writer {
+                        list.lock().unwrap().insert(_i);
+                    }
+                });
+            }
+        });
+        let list = list.into_inner().unwrap();
+        assert_eq!(list.len(), (writers * per_writer) as usize);
+        assert!(list.iter().copied().eq(0..writers * per_writer));
+        assert_invariants(&list);
+    }
+
+    #[test]
+    fn test_owned_iter_on_worker() {
+        let mut list: LinkedListSkipList<String, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(format!("key/{:04}", _i));
+        }
+        let owned = list.cursor_from(Bound::Included(&"key/0500".to_string())).into_owned_iter();
+        let worker = std::thread::spawn(move || owned.collect::<Vec<String>>());
+        // The iterator no longer borrows the list, so it can be written while the worker runs.
+        list.insert("key/1001".to_string());
+        assert_eq!(list.len(), 1001);
+        let collected = worker.join().unwrap();
+        assert!(collected.iter().cloned().eq((500..1000).map(|key| format!("key/{:04}", key))));
+        list.insert("key/1000".to_string());
+    }
+
+    #[test]
+    fn test_immutable_view_threads() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..10_000 {
+            list.insert(_i * 2);
+        }
+        let view = list.view();
+        std::thread::scope(|scope| {
+            let readers: Vec<_> = (0..4)
+                .map(|reader| {
+                    scope.spawn(move || {
+                        let mut found = 0;
+                        for key in (reader..20_000).step_by(4) {
+                            assert_eq!(view.contains(&key), key % 2 == 0);
+                            found += view.get(&key).is_some() as usize;
+                        }
+                        let lower = reader * 1000;
+                        assert!(view.range(lower..lower + 10).copied().eq((lower..lower + 10).filter(|key| key % 2 == 0)));
+                        assert!(view.range(..=6).copied().eq([0, 2, 4, 6]));
+                        assert!(view.range((Bound::Excluded(19_994), Bound::Unbounded)).copied().eq([19_996, 19_998]));
+                        (found, view.iter().count())
+                    })
+                })
+                .collect();
+            let results: Vec<(usize, usize)> = readers.into_iter().map(|reader| reader.join().unwrap()).collect();
+            assert_eq!(results.iter().map(|(found, _)| found).sum::<usize>(), 10_000);
+            assert!(results.iter().all(|(_, count)| *count == 10_000));
+        });
+    }
+
+    #[test]
+    fn test_dump() {
         let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        assert_eq!(list.dump(), "");
+        for (key, height) in [(1, 1), (2, 3), (3, 1), (4, 2)] {
+            list.insert_with_height(key, height);
+        }
+        let expected = "-> [ 2 ]-> None\n\
+                        -> [ 2 ]-> [ 4 ]-> None\n\
+                        -> [ 1 ]-> [ 2 ]-> [ 3 ]-> [ 4 ]-> None\n";
+        assert_eq!(list.dump(), expected);
+    }
+
+    #[test]
+    fn test_access_adaptive_promotion() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new()
+            .with_promotion(PromotionStrategy::AccessAdaptive);
+        for _i in 0..1000 {
+            list.insert_with_height(_i, 1);
+        }
+        let hot: Vec<i32> = (0..1000).step_by(100).collect();
+        for _ in 0..200 {
+            for key in &hot {
+                assert_eq!(list.access(key), Some(key));
+            }
+        }
+        for _i in 0..1000 {
+            assert_eq!(list.access(&_i), Some(&_i));
+        }
+        assert_eq!(list.access(&1000), None);
+        assert_invariants(&list);
+        assert!(list.iter().copied().eq(0..1000));
+
+        let height = |key: &i32| unsafe { (*list.find_first_not_before(|k| k < key).unwrap().as_ptr()).height() };
+        let hot_height = hot.iter().map(height).sum::<usize>() as f64 / hot.len() as f64;
+        let cold_height = (0..1000).filter(|key| !hot.contains(key)).map(|key| height(&key)).sum::<usize>() as f64 / 990.0;
+        assert!(hot_height > cold_height + 5.0, "hot keys {} levels, cold keys {} levels", hot_height, cold_height);
+        assert_eq!(list.get_max_height(), LinkedListSkipList::<'static, i32, { 2_usize.pow(6) }>::height_cap(1000));
+
+        let mut eager: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
         for _i in 0..100 {
+            eager.insert_with_height(_i, 1);
+        }
+        for _ in 0..100 {
+            eager.access(&50);
+        }
+        assert_eq!(eager.get_max_height(), 1);
+    }
+
+    #[test]
+    fn test_cursor_reset() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in (0..100).rev() {
             list.insert(_i);
         }
+        let mut cursor = list.cursor();
+        let first: Vec<i32> = cursor.by_ref().copied().collect();
+        assert_eq!(first, (0..100).collect::<Vec<_>>());
+        assert!(!cursor.valid());
+
+        cursor.reset();
+        let replay: Vec<i32> = cursor.by_ref().copied().collect();
+        assert_eq!(replay, first);
+
+        cursor.seek(&50).unwrap();
+        cursor.reset();
+        cursor.advance();
+        assert_eq!(cursor.key(), Some(&0));
+
+        // Seeking to the first key lands on it, so stepping forward yields the one after.
+        cursor.seek(&50).unwrap();
+        cursor.seek_to_first();
+        assert_eq!(cursor.key(), Some(&0));
+        assert_eq!(cursor.next(), Some(&1));
+        let empty: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let mut empty_cursor = empty.cursor();
+        empty_cursor.seek_to_first();
+        assert!(!empty_cursor.valid());
+
         let mut iter = list.into_iter();
-        iter.seek(&50);
-        assert_eq!(iter.key().unwrap(), &50);
+        iter.skip_forward(1000);
+        iter.reset();
+        assert!(iter.copied().eq(0..100));
     }
-}
 
+    #[test]
+    fn test_case_insensitive_keys() {
+        type Word = TransformedKey<String, AsciiCaseInsensitive>;
+        let word = |word: &str| Word::new(word.to_string());
+        let mut list: LinkedListSkipList<Word, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for key in ["banana", "Apple", "cherry", "BANANA split", "apricot"] {
+            list.insert(word(key));
+        }
+        let stored: Vec<&str> = list.iter().map(|key| key.key().as_str()).collect();
+        assert_eq!(stored, vec!["Apple", "apricot", "banana", "BANANA split", "cherry"]);
+        for query in ["apple", "APPLE", "aPpLe"] {
+            assert_eq!(list.get(&word(query)).map(|key| key.key().as_str()), Some("Apple"));
+        }
+        assert!(list.contains(&word("Banana Split")));
+        assert!(!list.contains(&word("apples")));
+        assert_eq!(list.get(&word("CHERRY")).unwrap().as_ref(), b"cherry");
+
+        // Keys equal ignoring case are the same key.
+        assert_eq!(list.replace(word("CHERRY")).map(Word::into_inner), Some("cherry".to_string()));
+        assert_eq!(list.len(), 5);
+        assert_eq!(list.max().map(|key| key.key().as_str()), Some("CHERRY"));
+    }
+
+    #[test]
+    fn test_boxed_byte_keys() {
+        let mut list: BoxedSkipList<[u8], { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        let keys: Vec<&[u8]> = vec![b"pear", b"", b"apple", b"fig", b"apples", b"a", b"banana split"];
+        for key in &keys {
+            list.insert(Box::from(*key));
+        }
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert!(list.iter().map(|key| &**key).eq(sorted.iter().copied()));
+        for key in &keys {
+            assert_eq!(list.get(*key).map(|key| &**key), Some(*key));
+        }
+        assert_eq!(list.get(b"app".as_slice()), None);
+        assert_eq!(list.get(b"zebra".as_slice()), None);
+
+        let mut strings: BoxedSkipList<str, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for word in ["delta", "alpha", "charlie", "bravo"] {
+            strings.insert(Box::from(word));
+        }
+        assert!(strings.iter().map(|key| &**key).eq(["alpha", "bravo", "charlie", "delta"]));
+        assert_eq!(strings.get("charlie").map(|key| &**key), Some("charlie"));
+    }
+}
@@ -0,0 +1,555 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::ops::{Bound, RangeBounds};
+
+use crate::memtable::linkedlist_skiplist::LinkedListSkipList;
+
+/// Reasons a `SkipMap::compare_and_insert` did not write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CasError {
+    /// The key is present but its value differs from the expected value.
+    Mismatch,
+    /// A value was expected but the key is absent.
+    Absent,
+    /// The key was expected to be absent but is present.
+    Present,
+}
+
+impl Display for CasError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CasError::Mismatch => write!(f, "present value does not match the expected value"),
+            CasError::Absent => write!(f, "expected a value but the key is absent"),
+            CasError::Present => write!(f, "expected the key to be absent but it is present"),
+        }
+    }
+}
+
+impl Error for CasError {}
+
+/// An ordered map backed by a `LinkedListSkipList`. Unlike the list it only exposes safe
+/// operations and places no bounds on keys other than `Ord`.
+pub struct SkipMap<K: Ord, V, const MAX_HEIGHT: usize = 32> {
+    list: LinkedListSkipList<'static, Entry<K, V>, MAX_HEIGHT>,
+    // Measures the bytes a value holds outside its entry, and the total for every stored value.
+    value_size: fn(&V) -> usize,
+    value_bytes: usize,
+}
+
+impl<K: Ord, V, const MAX_HEIGHT: usize> SkipMap<K, V, MAX_HEIGHT> {
+    pub fn new() -> Self {
+        Self {
+            list: LinkedListSkipList::new(),
+            value_size: |_| 0,
+            value_bytes: 0,
+        }
+    }
+
+    /// Sets how `memory_usage` measures the bytes a value holds outside the map, such as the
+    /// buffer of a `Vec`. Without it only the inline size of values is counted.
+    pub fn with_value_size(mut self, value_size: fn(&V) -> usize) -> Self {
+        self.value_size = value_size;
+        self.value_bytes = self.list.iter().map(|entry| value_size(entry.value())).sum();
+        self
+    }
+
+    /// Returns the bytes used by the map's nodes plus the bytes its values hold as measured by
+    /// the function given to `with_value_size`. Overwriting a value replaces its bytes in the
+    /// total rather than adding to it.
+    pub fn memory_usage(&self) -> usize {
+        self.list.memory_usage() + self.value_bytes
+    }
+
+    // takes a value no longer stored out of the value total.
+    fn uncount(&mut self, removed: Option<&V>) {
+        self.value_bytes -= removed.map_or(0, self.value_size);
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Inserts `value` under `key`, returning the value previously stored under the key.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let added = (self.value_size)(&value);
+        let replaced = self.list.replace(Entry::new(key, value)).map(Entry::into_value);
+        self.value_bytes += added;
+        self.uncount(replaced.as_ref());
+        replaced
+    }
+
+    /// Returns the value stored under `key`.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list
+            .iter_from_by(|entry| entry.key().borrow() < key)
+            .next()
+            .filter(|entry| entry.key().borrow() == key)
+            .map(Entry::value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Stores `value` under `key` only if the value currently stored equals `expected`, or if the
+    /// key is absent when `expected` is None. The key is searched for once.
+    pub fn compare_and_insert(&mut self, key: K, value: V, expected: Option<&V>) -> Result<(), CasError>
+    where
+        V: PartialEq,
+    {
+        let added = (self.value_size)(&value);
+        let mut error = None;
+        let written = self.list.insert_if(Entry::new(key, value), |present| {
+            error = match (present.map(Entry::value), expected) {
+                (Some(present), Some(expected)) if present == expected => None,
+                (None, None) => None,
+                (Some(_), Some(_)) => Some(CasError::Mismatch),
+                (None, Some(_)) => Some(CasError::Absent),
+                (Some(_), None) => Some(CasError::Present),
+            };
+            error.is_none()
+        });
+        match (written, error) {
+            (Ok(replaced), _) => {
+                self.value_bytes += added;
+                self.uncount(replaced.as_ref().map(Entry::value));
+                Ok(())
+            }
+            (Err(_), Some(error)) => Err(error),
+            (Err(_), None) => unreachable!(),
+        }
+    }
+
+    /// Applies `f` to the value stored under `key` in place. Returns false without inserting
+    /// anything if the key is absent.
+    pub fn update<Q, F>(&mut self, key: &Q, f: F) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+        F: FnOnce(&mut V),
+    {
+        match self.list.first_not_before_mut(|entry| entry.key().borrow() < key) {
+            Some(entry) if entry.key().borrow() == key => {
+                let before = (self.value_size)(entry.value());
+                f(entry.value_mut());
+                self.value_bytes = self.value_bytes + (self.value_size)(entry.value()) - before;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes the entry stored under `key`, returning its value.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let removed = self
+            .list
+            .take_by(|entry| entry.key().borrow() < key, |entry| entry.key().borrow() == key)
+            .map(Entry::into_value);
+        self.uncount(removed.as_ref());
+        removed
+    }
+
+    /// Returns an iterator over the entries in ascending key order.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&K, &V)> {
+        self.list.iter().map(Entry::pair)
+    }
+
+    /// Returns an iterator over the entries whose keys fall within `range`, in ascending order.
+    pub fn range<'b, Q, R>(&'b self, range: R) -> impl Iterator<Item = (&'b K, &'b V)> + 'b
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized + 'b,
+        R: RangeBounds<Q> + 'b,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.list.iter_from_by(|entry| entry.key().borrow() < start),
+            Bound::Excluded(start) => self.list.iter_from_by(|entry| entry.key().borrow() <= start),
+            Bound::Unbounded => self.list.iter(),
+        };
+        start
+            .take_while(move |entry| match range.end_bound() {
+                Bound::Included(end) => entry.key().borrow() <= end,
+                Bound::Excluded(end) => entry.key().borrow() < end,
+                Bound::Unbounded => true,
+            })
+            .map(Entry::pair)
+    }
+}
+
+impl<K: Ord, V, const MAX_HEIGHT: usize> Default for SkipMap<K, V, MAX_HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Builds a map from the entries of a `BTreeMap`, appending them in order without searching.
+impl<K: Ord, V, const MAX_HEIGHT: usize> From<BTreeMap<K, V>> for SkipMap<K, V, MAX_HEIGHT> {
+    fn from(entries: BTreeMap<K, V>) -> Self {
+        let mut map = Self::new();
+        for (key, value) in entries {
+            // Safety: a map yields its keys strictly ascending.
+            unsafe { map.list.insert_ascending_unchecked(Entry::new(key, value)) };
+        }
+        map
+    }
+}
+
+/// An ordered multimap backed by a `LinkedListSkipList`, for uses such as secondary indexes where
+/// a key has many values. Inserting under a present key adds the value to a chain of siblings
+/// kept in the key's one node, rather than replacing the value, so keys are ordered as in a
+/// `SkipMap` and a key's values in the order they were inserted.
+pub struct MultiSkipList<K: Ord, V, const MAX_HEIGHT: usize = 32> {
+    list: LinkedListSkipList<'static, Entry<K, Vec<V>>, MAX_HEIGHT>,
+    values: usize,
+}
+
+impl<K: Ord, V, const MAX_HEIGHT: usize> MultiSkipList<K, V, MAX_HEIGHT> {
+    pub fn new() -> Self {
+        Self {
+            list: LinkedListSkipList::new(),
+            values: 0,
+        }
+    }
+
+    /// Returns the number of values across every key.
+    pub fn len(&self) -> usize {
+        self.values
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values == 0
+    }
+
+    /// Returns the number of distinct keys.
+    pub fn key_count(&self) -> usize {
+        self.list.len()
+    }
+
+    /// Adds `value` after any values already stored under `key`.
+    pub fn insert(&mut self, key: K, value: V) {
+        self.values += 1;
+        match self.list.first_not_before_mut(|entry| *entry.key() < key) {
+            Some(entry) if *entry.key() == key => entry.value_mut().push(value),
+            _ => {
+                self.list.replace(Entry::new(key, vec![value]));
+            }
+        }
+    }
+
+    /// Returns the values stored under `key` in the order they were inserted.
+    pub fn get_all<Q>(&self, key: &Q) -> impl Iterator<Item = &V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list
+            .iter_from_by(|entry| entry.key().borrow() < key)
+            .next()
+            .filter(|entry| entry.key().borrow() == key)
+            .into_iter()
+            .flat_map(|entry| entry.value())
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_all(key).next().is_some()
+    }
+
+    /// Removes `key`, returning its values in the order they were inserted.
+    pub fn remove_all<Q>(&mut self, key: &Q) -> Vec<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let removed = self
+            .list
+            .take_by(|entry| entry.key().borrow() < key, |entry| entry.key().borrow() == key)
+            .map_or_else(Vec::new, Entry::into_value);
+        self.values -= removed.len();
+        removed
+    }
+
+    /// Returns an iterator over every value in ascending key order, and in insertion order for
+    /// the values of one key.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.list.iter().flat_map(|entry| {
+            let (key, values) = entry.pair();
+            values.iter().map(move |value| (key, value))
+        })
+    }
+}
+
+impl<K: Ord, V, const MAX_HEIGHT: usize> Default for MultiSkipList<K, V, MAX_HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A key and value stored in the list, ordered by key alone. The list's head holds the default
+// `Entry(None)` so neither the key nor the value need a `Default` implementation.
+struct Entry<K, V>(Option<(K, V)>);
+
+impl<K, V> Entry<K, V> {
+    fn new(key: K, value: V) -> Self {
+        Self(Some((key, value)))
+    }
+
+    fn key(&self) -> &K {
+        &self.0.as_ref().expect("the head entry is never exposed").0
+    }
+
+    fn value(&self) -> &V {
+        &self.0.as_ref().expect("the head entry is never exposed").1
+    }
+
+    fn value_mut(&mut self) -> &mut V {
+        &mut self.0.as_mut().expect("the head entry is never exposed").1
+    }
+
+    fn pair(&self) -> (&K, &V) {
+        let (key, value) = self.0.as_ref().expect("the head entry is never exposed");
+        (key, value)
+    }
+
+    fn into_value(self) -> V {
+        self.0.expect("the head entry is never exposed").1
+    }
+}
+
+impl<K, V> Default for Entry<K, V> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<K: Ord, V> PartialEq for Entry<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, V> Eq for Entry<K, V> {}
+
+impl<K: Ord, V> PartialOrd for Entry<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> Ord for Entry<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.0, &other.0) {
+            (Some((key, _)), Some((other_key, _))) => key.cmp(other_key),
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memtable::linkedlist_skiplist::seed_test_rng;
+
+    // Deliberately implements neither Display nor Default.
+    #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    struct UserId {
+        region: u8,
+        id: u32,
+    }
+
+    fn user(id: u32) -> UserId {
+        UserId { region: (id % 3) as u8, id }
+    }
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut map: SkipMap<UserId, String> = SkipMap::new();
+        for id in 0..100 {
+            assert_eq!(map.insert(user(id), format!("user {}", id)), None);
+        }
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get(&user(42)), Some(&"user 42".to_string()));
+        assert_eq!(map.insert(user(42), "replaced".to_string()), Some("user 42".to_string()));
+        assert_eq!(map.get(&user(42)), Some(&"replaced".to_string()));
+        assert_eq!(map.len(), 100);
+
+        assert_eq!(map.remove(&user(42)), Some("replaced".to_string()));
+        assert_eq!(map.remove(&user(42)), None);
+        assert!(!map.contains_key(&user(42)));
+        assert_eq!(map.get(&user(100)), None);
+        assert_eq!(map.len(), 99);
+    }
+
+    #[test]
+    fn test_memory_usage_overwrite() {
+        let mut map: SkipMap<u32, Vec<u8>> = SkipMap::new().with_value_size(Vec::len);
+        map.insert(1, vec![0; 100]);
+        map.insert(2, vec![0; 10]);
+        let usage = map.memory_usage();
+
+        assert_eq!(map.insert(1, vec![0; 150]).map(|value| value.len()), Some(100));
+        assert_eq!(map.memory_usage(), usage + 50);
+        assert_eq!(map.insert(1, vec![0; 20]).map(|value| value.len()), Some(150));
+        assert_eq!(map.memory_usage(), usage - 80);
+        assert_eq!(map.compare_and_insert(2, vec![0; 40], Some(&vec![0; 10])), Ok(()));
+        assert_eq!(map.memory_usage(), usage - 50);
+        assert!(map.update(&2, |value| value.truncate(5)));
+        assert_eq!(map.memory_usage(), usage - 85);
+
+        let node_usage = map.memory_usage() - 25;
+        map.remove(&1);
+        assert_eq!(map.memory_usage(), node_usage / 3 * 2 + 5);
+
+        // Values stored before the size function is set are counted once it is.
+        let mut map: SkipMap<u32, Vec<u8>> = SkipMap::new();
+        let empty = map.memory_usage();
+        map.insert(7, vec![0; 30]);
+        let unsized_usage = map.memory_usage();
+        let mut map = map.with_value_size(Vec::len);
+        assert_eq!(map.memory_usage(), unsized_usage + 30);
+        map.remove(&7);
+        assert_eq!(map.memory_usage(), empty);
+    }
+
+    #[test]
+    fn test_iter_sorted() {
+        seed_test_rng();
+        let mut map: SkipMap<UserId, u32> = SkipMap::new();
+        let mut ids: Vec<u32> = (0..500).collect();
+        fastrand::shuffle(&mut ids);
+        for id in ids {
+            map.insert(user(id), id);
+        }
+        let mut expected: Vec<UserId> = (0..500).map(user).collect();
+        expected.sort();
+        assert!(map.iter().map(|(key, _)| key).eq(expected.iter()));
+        assert!(map.iter().all(|(key, value)| key.id == *value));
+        assert!(map.iter().rev().map(|(key, _)| key).eq(expected.iter().rev()));
+    }
+
+    #[test]
+    fn test_from_btree_map() {
+        let entries: BTreeMap<UserId, String> = (0..500).map(|id| (user(id), format!("user {}", id))).collect();
+        let mut map: SkipMap<UserId, String> = SkipMap::from(entries.clone());
+        assert_eq!(map.len(), entries.len());
+        assert!(map.iter().eq(entries.iter()));
+        assert_eq!(map.get(&user(7)), Some(&"user 7".to_string()));
+        assert_eq!(map.insert(user(7), "seven".to_string()), Some("user 7".to_string()));
+        assert_eq!(map.insert(user(500), "user 500".to_string()), None);
+        assert_eq!(map.len(), 501);
+    }
+
+    #[test]
+    fn test_range() {
+        let mut map: SkipMap<i32, i32> = SkipMap::new();
+        for key in (0..100).step_by(2) {
+            map.insert(key, key * 10);
+        }
+        let keys = |range: (Bound<i32>, Bound<i32>)| map.range(range).map(|(key, _)| *key).collect::<Vec<i32>>();
+        assert_eq!(keys((Bound::Included(10), Bound::Excluded(16))), vec![10, 12, 14]);
+        assert_eq!(keys((Bound::Excluded(10), Bound::Included(16))), vec![12, 14, 16]);
+        assert_eq!(keys((Bound::Included(11), Bound::Included(15))), vec![12, 14]);
+        assert_eq!(keys((Bound::Unbounded, Bound::Excluded(4))), vec![0, 2]);
+        assert_eq!(keys((Bound::Included(96), Bound::Unbounded)), vec![96, 98]);
+        assert_eq!(map.range(200..).count(), 0);
+        assert_eq!(map.range(..).count(), 50);
+        assert_eq!(map.range(3..=5).collect::<Vec<(&i32, &i32)>>(), vec![(&4, &40)]);
+    }
+
+    #[test]
+    fn test_borrowed_lookup() {
+        let mut map: SkipMap<String, usize> = SkipMap::new();
+        for word in ["pear", "apple", "fig"] {
+            map.insert(word.to_string(), word.len());
+        }
+        assert_eq!(map.get("apple"), Some(&5));
+        assert_eq!(map.remove("fig"), Some(3));
+        assert!(map.iter().map(|(key, _)| key.as_str()).eq(["apple", "pear"]));
+    }
+
+    #[test]
+    fn test_update() {
+        let mut map: SkipMap<&str, Vec<u32>> = SkipMap::new();
+        map.insert("a", vec![1]);
+        map.insert("c", vec![3]);
+        assert!(map.update("a", |values| values.push(2)));
+        assert_eq!(map.get("a"), Some(&vec![1, 2]));
+
+        assert!(!map.update("b", |values| values.push(2)));
+        assert_eq!(map.get("b"), None);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("c"), Some(&vec![3]));
+    }
+
+    #[test]
+    fn test_compare_and_insert() {
+        let mut map: SkipMap<u32, u64> = SkipMap::new();
+        assert_eq!(map.compare_and_insert(1, 10, None), Ok(()));
+        assert_eq!(map.get(&1), Some(&10));
+
+        assert_eq!(map.compare_and_insert(1, 11, Some(&10)), Ok(()));
+        assert_eq!(map.get(&1), Some(&11));
+
+        assert_eq!(map.compare_and_insert(1, 12, Some(&10)), Err(CasError::Mismatch));
+        assert_eq!(map.compare_and_insert(1, 12, None), Err(CasError::Present));
+        assert_eq!(map.get(&1), Some(&11));
+
+        assert_eq!(map.compare_and_insert(2, 20, Some(&10)), Err(CasError::Absent));
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn test_multi_skip_list() {
+        let mut index: MultiSkipList<String, u32> = MultiSkipList::new();
+        // Users indexed by city, inserted interleaved across cities.
+        for id in 0..30 {
+            index.insert(["paris", "lima", "oslo"][id as usize % 3].to_string(), id);
+        }
+        index.insert("berlin".to_string(), 99);
+        assert_eq!(index.len(), 31);
+        assert_eq!(index.key_count(), 4);
+        assert!(index.get_all("lima").copied().eq((1..30).step_by(3)));
+        assert!(index.get_all("oslo").copied().eq((2..30).step_by(3)));
+        assert!(index.get_all("berlin").copied().eq([99]));
+        assert_eq!(index.get_all("rome").count(), 0);
+        assert!(!index.contains_key("rome"));
+
+        let keys: Vec<&str> = index.iter().map(|(key, _)| key.as_str()).collect();
+        assert!(keys.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(keys.iter().filter(|key| **key == "paris").count(), 10);
+        assert_eq!(index.iter().next(), Some((&"berlin".to_string(), &99)));
+
+        assert_eq!(index.remove_all("paris"), (0..30).step_by(3).collect::<Vec<_>>());
+        assert!(index.remove_all("paris").is_empty());
+        assert_eq!(index.len(), 21);
+        assert_eq!(index.key_count(), 3);
+        index.insert("paris".to_string(), 100);
+        assert!(index.get_all("paris").copied().eq([100]));
+    }
+}
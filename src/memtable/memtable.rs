@@ -0,0 +1,522 @@
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::memtable::linkedlist_skiplist::LinkedListSkipList;
+
+/// A multi-version table of the writes made to each key, backed by a `LinkedListSkipList`. Every
+/// write is tagged with a sequence number and deletes are recorded as tombstones so older versions
+/// stay readable until they are garbage collected. Range deletes are kept apart from the versions
+/// as tombstones covering every key in a range.
+pub struct MemTable<K: Ord, V, const MAX_HEIGHT: usize = 32> {
+    list: LinkedListSkipList<'static, Version<K, V>, MAX_HEIGHT>,
+    // Range tombstones split into non-overlapping fragments keyed by the start of each.
+    fragments: BTreeMap<K, Fragment<K>>,
+    // Highest sequence number written so far.
+    last_seq: u64,
+    snapshots: Arc<SnapshotList>,
+}
+
+/// A consistent view of a `MemTable` as of the sequence number it was taken at. Reads through the
+/// snapshot ignore every later write, and versions it can see are kept by garbage collection for
+/// as long as it is held. The snapshot does not borrow the table so writes can continue.
+pub struct Snapshot {
+    seq: u64,
+    snapshots: Arc<SnapshotList>,
+}
+
+impl Snapshot {
+    /// Returns the sequence number the snapshot reads at.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+}
+
+impl Drop for Snapshot {
+    fn drop(&mut self) {
+        let mut live = self.snapshots.live.lock().unwrap();
+        if let Some(count) = live.get_mut(&self.seq) {
+            *count -= 1;
+            if *count == 0 {
+                live.remove(&self.seq);
+            }
+        }
+    }
+}
+
+// The number of live snapshots at each sequence number.
+#[derive(Default)]
+struct SnapshotList {
+    live: Mutex<BTreeMap<u64, usize>>,
+}
+
+impl<K: Ord, V, const MAX_HEIGHT: usize> MemTable<K, V, MAX_HEIGHT> {
+    pub fn new() -> Self {
+        Self {
+            list: LinkedListSkipList::new(),
+            fragments: BTreeMap::new(),
+            last_seq: 0,
+            snapshots: Arc::default(),
+        }
+    }
+
+    /// Returns the number of versions in the table, including tombstones.
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Records `value` as the version of `key` written at `seq`.
+    pub fn put(&mut self, key: K, seq: u64, value: V) {
+        self.last_seq = self.last_seq.max(seq);
+        self.list.replace(Version::new(key, seq, Some(value)));
+    }
+
+    /// Records a tombstone as the version of `key` written at `seq`.
+    pub fn delete(&mut self, key: K, seq: u64) {
+        self.last_seq = self.last_seq.max(seq);
+        self.list.replace(Version::new(key, seq, None));
+    }
+
+    /// Records a tombstone written at `seq` covering every key in `[start, end)`. Versions of
+    /// those keys older than `seq` read as deleted, without removing them from the table.
+    pub fn delete_range(&mut self, start: K, end: K, seq: u64)
+    where
+        K: Clone,
+    {
+        if start >= end {
+            return;
+        }
+        self.last_seq = self.last_seq.max(seq);
+        // Take out every fragment overlapping or touching the range, including one starting before
+        // it.
+        let before = self.fragments.range(..&start).next_back().filter(|(_, fragment)| fragment.end >= start);
+        let overlapping_starts: Vec<K> = before
+            .into_iter()
+            .chain(self.fragments.range(&start..=&end))
+            .map(|(fragment_start, _)| fragment_start.clone())
+            .collect();
+        let overlapping: Vec<(K, Fragment<K>)> = overlapping_starts
+            .into_iter()
+            .map(|fragment_start| {
+                let fragment = self.fragments.remove(&fragment_start).unwrap();
+                (fragment_start, fragment)
+            })
+            .collect();
+
+        // Cut the union into pieces at every boundary and put back each piece with the sequence
+        // numbers covering it, merging neighbours covered by the same sequence numbers.
+        let mut bounds = vec![start.clone(), end.clone()];
+        for (fragment_start, fragment) in &overlapping {
+            bounds.push(fragment_start.clone());
+            bounds.push(fragment.end.clone());
+        }
+        bounds.sort();
+        bounds.dedup();
+        let mut pending: Option<(K, Fragment<K>)> = None;
+        for bound in bounds.windows(2) {
+            let (lower, upper) = (&bound[0], &bound[1]);
+            let mut seqs: Vec<u64> = overlapping
+                .iter()
+                .filter(|(fragment_start, fragment)| fragment_start <= lower && *upper <= fragment.end)
+                .flat_map(|(_, fragment)| fragment.seqs.iter().copied())
+                .collect();
+            if start <= *lower && *upper <= end {
+                seqs.push(seq);
+            }
+            seqs.sort_unstable_by(|a, b| b.cmp(a));
+            seqs.dedup();
+            match &mut pending {
+                Some((_, fragment)) if fragment.end == *lower && fragment.seqs == seqs => fragment.end = upper.clone(),
+                _ => {
+                    if let Some((fragment_start, fragment)) = pending.take() {
+                        self.fragments.insert(fragment_start, fragment);
+                    }
+                    if !seqs.is_empty() {
+                        pending = Some((lower.clone(), Fragment { end: upper.clone(), seqs }));
+                    }
+                }
+            }
+        }
+        if let Some((fragment_start, fragment)) = pending {
+            self.fragments.insert(fragment_start, fragment);
+        }
+    }
+
+    /// Returns the newest value of `key`, or None if it was never written or its newest version is
+    /// a tombstone or covered by a newer range tombstone.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list
+            .iter_from_by(|version| version.key().borrow() < key)
+            .next()
+            .filter(|version| version.key().borrow() == key)
+            .filter(|version| !self.is_range_deleted(version.key(), version.seq(), u64::MAX))
+            .and_then(Version::value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns every version as `(key, seq, value)` ordered by key then newest first, with None as
+    /// the value of a tombstone. Versions covered by a newer range tombstone are skipped.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, u64, Option<&V>)> {
+        self.list
+            .iter()
+            .map(Version::parts)
+            .filter(|(key, seq, _)| !self.is_range_deleted(key, *seq, u64::MAX))
+    }
+
+    /// Returns each key written after `seq` along with the sequence number of its newest write,
+    /// in key order, for feeding changes to a consumer which has seen everything up to `seq`.
+    /// Deletes are included, range deletes are not and can be found in `range_tombstones`.
+    pub fn iter_since(&self, seq: u64) -> impl Iterator<Item = (&K, u64)> {
+        let mut last_key: Option<&K> = None;
+        self.list.iter().map(Version::parts).filter_map(move |(key, version_seq, _)| {
+            // Only the newest version of each key is reported.
+            if last_key == Some(key) {
+                return None;
+            }
+            last_key = Some(key);
+            (version_seq > seq).then_some((key, version_seq))
+        })
+    }
+
+    /// Returns the range tombstones as `(start, end, seq)`, split into fragments which either do
+    /// not overlap or cover exactly the same keys, ordered by start then newest first.
+    pub fn range_tombstones(&self) -> impl Iterator<Item = (&K, &K, u64)> {
+        self.fragments
+            .iter()
+            .flat_map(|(start, fragment)| fragment.seqs.iter().map(move |seq| (start, &fragment.end, *seq)))
+    }
+
+    /// Takes a snapshot of the table as of the highest sequence number written so far.
+    pub fn snapshot(&self) -> Snapshot {
+        *self.snapshots.live.lock().unwrap().entry(self.last_seq).or_default() += 1;
+        Snapshot {
+            seq: self.last_seq,
+            snapshots: self.snapshots.clone(),
+        }
+    }
+
+    /// Returns the sequence number at or below which versions can be garbage collected without
+    /// changing what any live snapshot reads, for passing to `iter_gc`.
+    pub fn gc_seq(&self) -> u64 {
+        let live = self.snapshots.live.lock().unwrap();
+        live.keys().next().copied().unwrap_or(self.last_seq)
+    }
+
+    /// Returns the value of `key` as seen by `snapshot`.
+    pub fn get_at<Q>(&self, key: &Q, snapshot: &Snapshot) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.list
+            .iter_from_by(|version| {
+                let (version_key, seq, _) = version.parts();
+                version_key.borrow() < key || (version_key.borrow() == key && seq > snapshot.seq)
+            })
+            .next()
+            .filter(|version| version.key().borrow() == key)
+            .filter(|version| !self.is_range_deleted(version.key(), version.seq(), snapshot.seq))
+            .and_then(Version::value)
+    }
+
+    /// Returns every key and its value as seen by `snapshot`, in ascending order.
+    pub fn iter_at<'b>(&'b self, snapshot: &Snapshot) -> impl Iterator<Item = (&'b K, &'b V)> + 'b {
+        let read_seq = snapshot.seq;
+        let mut last_key: Option<&K> = None;
+        self.list
+            .iter()
+            .map(Version::parts)
+            .filter(move |(key, seq, _)| {
+                // The first version of each key at or below the snapshot is the one it sees.
+                if *seq > read_seq || last_key == Some(*key) {
+                    return false;
+                }
+                last_key = Some(*key);
+                !self.is_range_deleted(key, *seq, read_seq)
+            })
+            .filter_map(|(key, _, value)| Some((key, value?)))
+    }
+
+    // returns true if a range tombstone written after `seq` and no later than `read_seq` covers
+    // `key`.
+    fn is_range_deleted(&self, key: &K, seq: u64, read_seq: u64) -> bool {
+        self.fragments
+            .range(..=key)
+            .next_back()
+            .filter(|(_, fragment)| *key < fragment.end)
+            .and_then(|(_, fragment)| fragment.seqs.iter().find(|tombstone_seq| **tombstone_seq <= read_seq))
+            .is_some_and(|tombstone_seq| *tombstone_seq > seq)
+    }
+
+    /// Returns the versions a compaction would keep once no snapshot can read at or below
+    /// `gc_seq`. Versions newer than `gc_seq` are all kept. Of the rest only the newest version of
+    /// each key is kept, and only if it is neither a tombstone nor covered by a range tombstone
+    /// at or below `gc_seq`.
+    pub fn iter_gc(&self, gc_seq: u64) -> impl Iterator<Item = (&K, u64, Option<&V>)> {
+        let mut last_key: Option<&K> = None;
+        let mut settled = false;
+        self.list.iter().map(Version::parts).filter(move |(key, seq, value)| {
+            if last_key != Some(*key) {
+                last_key = Some(*key);
+                settled = false;
+            }
+            if *seq > gc_seq {
+                return true;
+            }
+            // Only the newest version at or below `gc_seq` is visible, older ones are shadowed.
+            let visible = !settled;
+            settled = true;
+            visible && value.is_some() && !self.is_range_deleted(key, *seq, gc_seq)
+        })
+    }
+}
+
+impl<K: Ord, V, const MAX_HEIGHT: usize> Default for MemTable<K, V, MAX_HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// A write to a key, ordered by key and then newest sequence number first. A None value is a
+// tombstone. The list's head holds the default `Version(None)` so neither the key nor the value
+// need a `Default` implementation.
+struct Version<K, V>(Option<(K, u64, Option<V>)>);
+
+impl<K, V> Version<K, V> {
+    fn new(key: K, seq: u64, value: Option<V>) -> Self {
+        Self(Some((key, seq, value)))
+    }
+
+    fn key(&self) -> &K {
+        &self.0.as_ref().expect("the head version is never exposed").0
+    }
+
+    fn seq(&self) -> u64 {
+        self.0.as_ref().expect("the head version is never exposed").1
+    }
+
+    fn value(&self) -> Option<&V> {
+        self.0.as_ref().expect("the head version is never exposed").2.as_ref()
+    }
+
+    fn parts(&self) -> (&K, u64, Option<&V>) {
+        let (key, seq, value) = self.0.as_ref().expect("the head version is never exposed");
+        (key, *seq, value.as_ref())
+    }
+}
+
+// The sequence numbers of the range tombstones covering `[start, end)`, newest first. The start
+// is the key the fragment is stored under.
+struct Fragment<K> {
+    end: K,
+    seqs: Vec<u64>,
+}
+
+impl<K, V> Default for Version<K, V> {
+    fn default() -> Self {
+        Self(None)
+    }
+}
+
+impl<K: Ord, V> PartialEq for Version<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<K: Ord, V> Eq for Version<K, V> {}
+
+impl<K: Ord, V> PartialOrd for Version<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord, V> Ord for Version<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.0, &other.0) {
+            (Some((key, seq, _)), Some((other_key, other_seq, _))) => {
+                key.cmp(other_key).then_with(|| other_seq.cmp(seq))
+            }
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions() -> MemTable<&'static str, String> {
+        let mut table = MemTable::new();
+        table.put("a", 1, "a1".to_string());
+        table.put("a", 3, "a3".to_string());
+        table.delete("a", 5);
+        table.put("b", 2, "b2".to_string());
+        table.delete("b", 4);
+        table.put("b", 6, "b6".to_string());
+        table.delete("c", 7);
+        table.put("d", 1, "d1".to_string());
+        table
+    }
+
+    fn collect<'a>(versions: impl Iterator<Item = (&'a &'static str, u64, Option<&'a String>)>) -> Vec<(&'static str, u64, Option<&'a str>)> {
+        versions.map(|(key, seq, value)| (*key, seq, value.map(String::as_str))).collect()
+    }
+
+    #[test]
+    fn test_versions() {
+        let table = versions();
+        assert_eq!(table.len(), 8);
+        assert_eq!(table.get("a"), None);
+        assert_eq!(table.get("b"), Some(&"b6".to_string()));
+        assert_eq!(table.get("c"), None);
+        assert_eq!(table.get("d"), Some(&"d1".to_string()));
+        assert_eq!(table.get("e"), None);
+        assert_eq!(
+            collect(table.iter()),
+            vec![
+                ("a", 5, None),
+                ("a", 3, Some("a3")),
+                ("a", 1, Some("a1")),
+                ("b", 6, Some("b6")),
+                ("b", 4, None),
+                ("b", 2, Some("b2")),
+                ("c", 7, None),
+                ("d", 1, Some("d1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_iter_gc() {
+        let table = versions();
+        assert_eq!(collect(table.iter_gc(0)), collect(table.iter()));
+        assert_eq!(
+            collect(table.iter_gc(4)),
+            vec![("a", 5, None), ("a", 3, Some("a3")), ("b", 6, Some("b6")), ("c", 7, None), ("d", 1, Some("d1"))]
+        );
+        assert_eq!(
+            collect(table.iter_gc(5)),
+            vec![("b", 6, Some("b6")), ("c", 7, None), ("d", 1, Some("d1"))]
+        );
+        assert_eq!(collect(table.iter_gc(u64::MAX)), vec![("b", 6, Some("b6")), ("d", 1, Some("d1"))]);
+    }
+
+    #[test]
+    fn test_delete_range() {
+        let mut table: MemTable<i32, String> = MemTable::new();
+        for key in 0..1000 {
+            table.put(key, 1, format!("value {}", key));
+        }
+        table.delete_range(100, 200, 2);
+        assert_eq!(table.len(), 1000);
+        for key in 0..1000 {
+            assert_eq!(table.contains_key(&key), !(100..200).contains(&key), "key {}", key);
+        }
+        assert!(table.iter().map(|(key, _, _)| *key).eq((0..100).chain(200..1000)));
+        assert_eq!(table.range_tombstones().collect::<Vec<_>>(), vec![(&100, &200, 2)]);
+
+        // A newer write is visible over the range tombstone.
+        table.put(150, 3, "rewritten".to_string());
+        assert_eq!(table.get(&150), Some(&"rewritten".to_string()));
+
+        // Overlapping ranges are fragmented, neighbours covered by the same writes are merged.
+        table.delete_range(150, 300, 4);
+        table.delete_range(250, 400, 4);
+        table.delete_range(50, 60, 2);
+        table.delete_range(60, 100, 2);
+        assert_eq!(
+            table.range_tombstones().collect::<Vec<_>>(),
+            vec![(&50, &150, 2), (&150, &200, 4), (&150, &200, 2), (&200, &400, 4)]
+        );
+        for key in 0..1000 {
+            assert_eq!(table.contains_key(&key), !(50..400).contains(&key), "key {}", key);
+        }
+    }
+
+    #[test]
+    fn test_iter_gc_range_tombstone() {
+        let mut table: MemTable<&'static str, String> = MemTable::new();
+        table.put("a", 1, "a1".to_string());
+        table.put("b", 1, "b1".to_string());
+        table.put("b", 4, "b4".to_string());
+        table.put("c", 1, "c1".to_string());
+        table.delete_range("a", "c", 3);
+        assert_eq!(collect(table.iter_gc(2)), vec![("a", 1, Some("a1")), ("b", 4, Some("b4")), ("b", 1, Some("b1")), ("c", 1, Some("c1"))]);
+        assert_eq!(collect(table.iter_gc(3)), vec![("b", 4, Some("b4")), ("c", 1, Some("c1"))]);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut table: MemTable<&'static str, String> = MemTable::new();
+        table.put("a", 1, "a1".to_string());
+        table.put("b", 2, "b2".to_string());
+        table.put("c", 3, "c3".to_string());
+        let snapshot = table.snapshot();
+        assert_eq!(snapshot.seq(), 3);
+
+        table.put("a", 4, "a4".to_string());
+        table.delete("b", 5);
+        table.delete_range("c", "d", 6);
+        table.put("d", 7, "d7".to_string());
+        assert_eq!(table.get("a"), Some(&"a4".to_string()));
+        assert_eq!(table.get("b"), None);
+        assert_eq!(table.get("c"), None);
+
+        assert_eq!(table.get_at("a", &snapshot), Some(&"a1".to_string()));
+        assert_eq!(table.get_at("b", &snapshot), Some(&"b2".to_string()));
+        assert_eq!(table.get_at("c", &snapshot), Some(&"c3".to_string()));
+        assert_eq!(table.get_at("d", &snapshot), None);
+        let seen: Vec<(&str, &str)> = table.iter_at(&snapshot).map(|(key, value)| (*key, value.as_str())).collect();
+        assert_eq!(seen, vec![("a", "a1"), ("b", "b2"), ("c", "c3")]);
+
+        // Versions the snapshot reads survive garbage collection until it is dropped.
+        assert_eq!(table.gc_seq(), 3);
+        assert_eq!(
+            collect(table.iter_gc(table.gc_seq())),
+            vec![("a", 4, Some("a4")), ("a", 1, Some("a1")), ("b", 5, None), ("b", 2, Some("b2")), ("c", 3, Some("c3")), ("d", 7, Some("d7"))]
+        );
+        let newer = table.snapshot();
+        drop(snapshot);
+        assert_eq!(table.gc_seq(), 7);
+        assert_eq!(collect(table.iter_gc(table.gc_seq())), vec![("a", 4, Some("a4")), ("d", 7, Some("d7"))]);
+        let seen: Vec<(&str, &str)> = table.iter_at(&newer).map(|(key, value)| (*key, value.as_str())).collect();
+        assert_eq!(seen, vec![("a", "a4"), ("d", "d7")]);
+    }
+
+    #[test]
+    fn test_iter_since() {
+        let mut table: MemTable<i32, String> = MemTable::new();
+        for key in 0..100 {
+            table.put(key, key as u64 + 1, format!("value {}", key));
+        }
+        table.put(10, 101, "rewritten".to_string());
+        table.delete(20, 102);
+
+        let since = |seq| table.iter_since(seq).map(|(key, seq)| (*key, seq)).collect::<Vec<_>>();
+        assert_eq!(since(102), vec![]);
+        assert_eq!(since(100), vec![(10, 101), (20, 102)]);
+        let expected: Vec<(i32, u64)> = [(10, 101), (20, 102)].into_iter().chain((90..100).map(|key| (key, key as u64 + 1))).collect();
+        assert_eq!(since(90), expected);
+        assert_eq!(table.iter_since(0).count(), 100);
+    }
+}
@@ -0,0 +1,86 @@
+use std::iter::Peekable;
+
+/// Merges several ascending iterators into one ascending iterator. Items which compare equal
+/// across sources are yielded once, taken from the source listed first, so listing sources from
+/// newest to oldest lets the newest write of a key win.
+///
+/// Each step compares the head of every source, which suits merging the handful of memtables
+/// flushed together.
+pub struct MergingIterator<I: Iterator> {
+    sources: Vec<Peekable<I>>,
+}
+
+impl<I: Iterator> MergingIterator<I>
+where
+    I::Item: Ord,
+{
+    pub fn new<S: IntoIterator<Item = I>>(sources: S) -> Self {
+        Self {
+            sources: sources.into_iter().map(Iterator::peekable).collect(),
+        }
+    }
+}
+
+impl<I: Iterator> Iterator for MergingIterator<I>
+where
+    I::Item: Ord,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut smallest: Option<(usize, &I::Item)> = None;
+        for (i, source) in self.sources.iter_mut().enumerate() {
+            let Some(item) = source.peek() else {
+                continue;
+            };
+            // Strictly less so the first listed source wins a tie.
+            if smallest.is_none_or(|(_, smallest)| item < smallest) {
+                smallest = Some((i, item));
+            }
+        }
+        let (smallest, _) = smallest?;
+        let item = self.sources[smallest].next()?;
+        // Drop the versions of the same item from the sources after it.
+        for source in &mut self.sources {
+            source.next_if(|other| *other == item);
+        }
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_newest_wins() {
+        // Ordered by key alone, so the tag shows which source an item came from.
+        #[derive(Debug)]
+        struct Tagged(i32, &'static str);
+        impl PartialEq for Tagged {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+        impl Eq for Tagged {}
+        impl PartialOrd for Tagged {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Tagged {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        let newest = vec![Tagged(2, "newest"), Tagged(5, "newest")];
+        let middle = vec![Tagged(1, "middle"), Tagged(2, "middle"), Tagged(6, "middle")];
+        let oldest = vec![Tagged(1, "oldest"), Tagged(2, "oldest"), Tagged(5, "oldest"), Tagged(9, "oldest")];
+        let merged: Vec<(i32, &str)> = MergingIterator::new([newest.into_iter(), middle.into_iter(), oldest.into_iter(), Vec::new().into_iter()])
+            .map(|Tagged(key, tag)| (key, tag))
+            .collect();
+        assert_eq!(merged, vec![(1, "middle"), (2, "newest"), (5, "newest"), (6, "middle"), (9, "oldest")]);
+        assert_eq!(MergingIterator::new(Vec::<std::vec::IntoIter<i32>>::new()).next(), None);
+    }
+}
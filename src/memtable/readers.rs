@@ -0,0 +1,274 @@
+use std::cell::UnsafeCell;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+use crate::memtable::allocator::{Global, NodeAllocator};
+use crate::memtable::linkedlist_skiplist::{ImmutableView, LinkedListSkipList};
+
+/// Counts the readers traversing a list so a structural change, such as `split_off`, can wait
+/// until none are mid-traversal. Readers register with `read_guard` and the change is made while
+/// holding the guard returned by `quiesce`. `SharedSkipList` pairs a gate with the list it guards.
+#[derive(Default)]
+pub struct ReaderGate {
+    state: Mutex<GateState>,
+    // Signalled when the last reader leaves and when a quiesce ends.
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct GateState {
+    readers: usize,
+    quiescing: bool,
+}
+
+impl ReaderGate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a reader until the returned guard is dropped. Waits while the gate is quiesced.
+    pub fn read_guard(&self) -> ReadGuard<'_> {
+        let mut state = self.wait_while(self.lock(), |state| state.quiescing);
+        state.readers += 1;
+        ReadGuard { gate: self }
+    }
+
+    /// Stops new readers registering and waits for the registered readers to drop their guards.
+    /// New readers are let in again once the returned guard is dropped. Only one quiesce is held
+    /// at a time, a second waits for the first to end.
+    pub fn quiesce(&self) -> QuiesceGuard<'_> {
+        let mut state = self.wait_while(self.lock(), |state| state.quiescing);
+        state.quiescing = true;
+        drop(self.wait_while(state, |state| state.readers > 0));
+        QuiesceGuard { gate: self }
+    }
+
+    /// Returns the number of registered readers.
+    pub fn readers(&self) -> usize {
+        self.lock().readers
+    }
+
+    fn lock(&self) -> MutexGuard<'_, GateState> {
+        // The state is only a pair of counters, left consistent even if a holder panicked.
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn wait_while<'g>(&self, state: MutexGuard<'g, GateState>, condition: impl FnMut(&mut GateState) -> bool) -> MutexGuard<'g, GateState> {
+        self.changed.wait_while(state, condition).unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// A registered reader, created by `ReaderGate::read_guard`.
+pub struct ReadGuard<'g> {
+    gate: &'g ReaderGate,
+}
+
+impl Drop for ReadGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.gate.lock();
+        state.readers -= 1;
+        if state.readers == 0 {
+            self.gate.changed.notify_all();
+        }
+    }
+}
+
+/// Holds off new readers until dropped, created by `ReaderGate::quiesce`.
+pub struct QuiesceGuard<'g> {
+    gate: &'g ReaderGate,
+}
+
+impl Drop for QuiesceGuard<'_> {
+    fn drop(&mut self) {
+        self.gate.lock().quiescing = false;
+        self.gate.changed.notify_all();
+    }
+}
+
+/// A list shared between threads whose readers register with a `ReaderGate`, so a structural
+/// change such as `split_off` or `append` can be made once they have left. Reads go through the
+/// view of a `SharedRead` and changes through the list of a `Quiesced`, which is only handed out
+/// while no reader is registered.
+pub struct SharedSkipList<'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator = Global> {
+    list: UnsafeCell<LinkedListSkipList<'a, Key, MAX_HEIGHT, A>>,
+    gate: ReaderGate,
+}
+
+// Safety: readers only reach the list through an `ImmutableView`, which is Sync for these bounds,
+// and the list is only borrowed mutably, possibly from another thread, while the gate holds every
+// reader off.
+unsafe impl<'a, Key: Ord + Default + Send + Sync, const MAX_HEIGHT: usize, A: NodeAllocator + Send + Sync> Sync for SharedSkipList<'a, Key, MAX_HEIGHT, A> {}
+
+impl<'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> SharedSkipList<'a, Key, MAX_HEIGHT, A> {
+    pub fn new(list: LinkedListSkipList<'a, Key, MAX_HEIGHT, A>) -> Self {
+        Self {
+            list: UnsafeCell::new(list),
+            gate: ReaderGate::new(),
+        }
+    }
+
+    /// Registers a reader until the returned guard is dropped, waiting while the list is quiesced.
+    pub fn read(&self) -> SharedRead<'_, 'a, Key, MAX_HEIGHT, A> {
+        let guard = self.gate.read_guard();
+        SharedRead { shared: self, _guard: guard }
+    }
+
+    /// Blocks new readers and waits for the registered ones to drop their guards, then gives
+    /// mutable access to the list until the returned guard is dropped.
+    pub fn quiesce(&self) -> Quiesced<'_, 'a, Key, MAX_HEIGHT, A> {
+        let guard = self.gate.quiesce();
+        Quiesced { shared: self, _guard: guard }
+    }
+
+    /// Returns the number of registered readers.
+    pub fn readers(&self) -> usize {
+        self.gate.readers()
+    }
+
+    pub fn into_inner(self) -> LinkedListSkipList<'a, Key, MAX_HEIGHT, A> {
+        self.list.into_inner()
+    }
+}
+
+/// A registered reader of a `SharedSkipList`, created by `SharedSkipList::read`.
+pub struct SharedRead<'s, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator = Global> {
+    shared: &'s SharedSkipList<'a, Key, MAX_HEIGHT, A>,
+    _guard: ReadGuard<'s>,
+}
+
+impl<'s, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> SharedRead<'s, 'a, Key, MAX_HEIGHT, A> {
+    /// Returns a view of the list which cannot outlive the registration.
+    pub fn view(&self) -> ImmutableView<'_, 'a, Key, MAX_HEIGHT, A> {
+        // Safety: no `Quiesced` exists while a reader is registered.
+        unsafe { (*self.shared.list.get()).view() }
+    }
+}
+
+/// Mutable access to the list of a `SharedSkipList` with every reader held off, created by
+/// `SharedSkipList::quiesce`.
+pub struct Quiesced<'s, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator = Global> {
+    shared: &'s SharedSkipList<'a, Key, MAX_HEIGHT, A>,
+    _guard: QuiesceGuard<'s>,
+}
+
+impl<'s, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> Deref for Quiesced<'s, 'a, Key, MAX_HEIGHT, A> {
+    type Target = LinkedListSkipList<'a, Key, MAX_HEIGHT, A>;
+
+    fn deref(&self) -> &Self::Target {
+        // Safety: the gate lets in neither readers nor another quiesce while this guard exists.
+        unsafe { &*self.shared.list.get() }
+    }
+}
+
+impl<'s, 'a, Key: Ord + Default, const MAX_HEIGHT: usize, A: NodeAllocator> DerefMut for Quiesced<'s, 'a, Key, MAX_HEIGHT, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: as for `deref`, and the borrow of the guard keeps this the only reference.
+        unsafe { &mut *self.shared.list.get() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memtable::linkedlist_skiplist::seed_test_rng;
+    use crate::memtable::skiplist::SkipList;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{mpsc, Barrier};
+    use std::time::Duration;
+
+    #[test]
+    fn test_quiesce_waits_for_readers() {
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..1000 {
+            list.insert(_i);
+        }
+        let shared = SharedSkipList::new(list);
+        let readers = 4;
+        let registered = Barrier::new(readers + 1);
+        let released = AtomicUsize::new(0);
+        let late_reader_in = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            let releases: Vec<_> = (0..readers)
+                .map(|_| {
+                    let (release, on_release) = mpsc::channel::<()>();
+                    let (shared, registered, released) = (&shared, &registered, &released);
+                    scope.spawn(move || {
+                        let read = shared.read();
+                        registered.wait();
+                        assert_eq!(read.view().iter().count(), 1000);
+                        on_release.recv().unwrap();
+                        released.fetch_add(1, Ordering::SeqCst);
+                        drop(read);
+                    });
+                    release
+                })
+                .collect();
+            registered.wait();
+            assert_eq!(shared.readers(), readers);
+            // Let the readers go one at a time while the quiesce below waits for them.
+            scope.spawn(move || {
+                for release in releases {
+                    std::thread::sleep(Duration::from_millis(10));
+                    release.send(()).unwrap();
+                }
+            });
+
+            let mut quiesced = shared.quiesce();
+            assert_eq!(released.load(Ordering::SeqCst), readers);
+            assert_eq!(shared.readers(), 0);
+            let late_reader = scope.spawn(|| {
+                let read = shared.read();
+                late_reader_in.store(true, Ordering::SeqCst);
+                assert_eq!(read.view().len(), 500);
+            });
+            std::thread::sleep(Duration::from_millis(20));
+            assert!(!late_reader_in.load(Ordering::SeqCst));
+            let upper = quiesced.split_off(&500);
+            assert_eq!(upper.len(), 500);
+            drop(quiesced);
+            late_reader.join().unwrap();
+            assert!(late_reader_in.load(Ordering::SeqCst));
+        });
+        assert_eq!(shared.readers(), 0);
+    }
+
+    #[test]
+    fn test_split_off_racing_readers() {
+        seed_test_rng();
+        let mut list: LinkedListSkipList<i32, { 2_usize.pow(6) }> = LinkedListSkipList::new();
+        for _i in 0..2000 {
+            list.insert(_i);
+        }
+        let shared = SharedSkipList::new(list);
+        let done = AtomicBool::new(false);
+        std::thread::scope(|scope| {
+            for reader in 0..4 {
+                let (shared, done) = (&shared, &done);
+                scope.spawn(move || {
+                    let mut reads = 0;
+                    while !done.load(Ordering::SeqCst) || reads == 0 {
+                        // The halves are split and put back together under one quiesce, so a
+                        // reader always sees every key.
+                        let read = shared.read();
+                        let view = read.view();
+                        assert!(view.iter().copied().eq(0..2000));
+                        assert!(view.contains(&(reader * 499)));
+                        reads += 1;
+                    }
+                });
+            }
+            for _round in 0..50 {
+                let mut quiesced = shared.quiesce();
+                let upper = quiesced.split_off(&(fastrand::i32(1..2000)));
+                assert!(quiesced.iter().chain(upper.iter()).copied().eq(0..2000));
+                quiesced.append(upper);
+                drop(quiesced);
+                std::thread::yield_now();
+            }
+            done.store(true, Ordering::SeqCst);
+        });
+        let list = shared.into_inner();
+        assert_eq!(list.len(), 2000);
+        assert!(list.iter().copied().eq(0..2000));
+    }
+}
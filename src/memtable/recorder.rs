@@ -0,0 +1,134 @@
+use std::fmt::Debug;
+use std::io::{self, Write};
+
+use crate::memtable::linkedlist_skiplist::LinkedListSkipList;
+use crate::memtable::skiplist::SkipList;
+
+/// An operation made through a `RecordingList`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op<Key> {
+    Insert(Key),
+    Remove(Key),
+    Get(Key),
+}
+
+/// Wraps a list and records every insert, remove and get made through it, in order, so the
+/// sequence which led to a bug can be replayed into a fresh list with `replay`.
+pub struct RecordingList<'a, Key: Ord + Default, const MAX_HEIGHT: usize> {
+    list: LinkedListSkipList<'a, Key, MAX_HEIGHT>,
+    ops: Vec<Op<Key>>,
+}
+
+impl<'a, Key: Ord + Default + Clone, const MAX_HEIGHT: usize> RecordingList<'a, Key, MAX_HEIGHT> {
+    /// Records the operations made on `list` from now on. Keys already in it are not recorded.
+    pub fn new(list: LinkedListSkipList<'a, Key, MAX_HEIGHT>) -> Self {
+        Self { list, ops: Vec::new() }
+    }
+
+    /// Inserts `key`, returning the equal key it replaced.
+    pub fn insert(&mut self, key: Key) -> Option<Key> {
+        self.ops.push(Op::Insert(key.clone()));
+        self.list.replace(key)
+    }
+
+    pub fn remove(&mut self, key: &Key) -> bool {
+        self.ops.push(Op::Remove(key.clone()));
+        self.list.remove(key)
+    }
+
+    pub fn get(&mut self, key: &Key) -> Option<&Key> {
+        self.ops.push(Op::Get(key.clone()));
+        self.list.get(key)
+    }
+
+    /// Returns the operations recorded so far, oldest first.
+    pub fn ops(&self) -> &[Op<Key>] {
+        &self.ops
+    }
+
+    pub fn list(&self) -> &LinkedListSkipList<'a, Key, MAX_HEIGHT> {
+        &self.list
+    }
+
+    /// Writes the recorded operations to `w`, one per line, for attaching to a bug report.
+    pub fn write_log<W: Write>(&self, w: &mut W) -> io::Result<()>
+    where
+        Key: Debug,
+    {
+        for op in &self.ops {
+            writeln!(w, "{:?}", op)?;
+        }
+        Ok(())
+    }
+
+    /// Stops recording, returning the list and the operations recorded.
+    pub fn into_parts(self) -> (LinkedListSkipList<'a, Key, MAX_HEIGHT>, Vec<Op<Key>>) {
+        (self.list, self.ops)
+    }
+}
+
+/// Applies `ops` in order to a new list and returns it. Gets are made too, so a bug in a lookup
+/// reproduces along with the writes around it.
+pub fn replay<Key: Ord + Default, const MAX_HEIGHT: usize, I: IntoIterator<Item = Op<Key>>>(ops: I) -> LinkedListSkipList<'static, Key, MAX_HEIGHT> {
+    let mut list = LinkedListSkipList::new();
+    for op in ops {
+        match op {
+            Op::Insert(key) => {
+                list.replace(key);
+            }
+            Op::Remove(key) => {
+                list.remove(&key);
+            }
+            Op::Get(key) => {
+                list.get(&key);
+            }
+        }
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memtable::linkedlist_skiplist::seed_test_rng;
+
+    #[test]
+    fn test_replay_recorded_ops() {
+        seed_test_rng();
+        let mut recording: RecordingList<u32, { 2_usize.pow(6) }> = RecordingList::new(LinkedListSkipList::new());
+        for _i in 0..2000 {
+            let key = fastrand::u32(..500);
+            match fastrand::u8(..3) {
+                0 => {
+                    recording.insert(key);
+                }
+                1 => {
+                    recording.remove(&key);
+                }
+                _ => {
+                    recording.get(&key);
+                }
+            }
+        }
+        assert_eq!(recording.ops().len(), 2000);
+        let mut log = Vec::new();
+        recording.write_log(&mut log).unwrap();
+        assert_eq!(log.iter().filter(|byte| **byte == b'\n').count(), 2000);
+
+        let (list, ops) = recording.into_parts();
+        let replayed: LinkedListSkipList<u32, { 2_usize.pow(6) }> = replay(ops.clone());
+        assert_eq!(replayed.len(), list.len());
+        assert!(replayed.iter().eq(list.iter()));
+        // Replaying twice gives the same list again.
+        assert!(replay::<u32, { 2_usize.pow(6) }, _>(ops).iter().eq(list.iter()));
+
+        let mut recording: RecordingList<&str, { 2_usize.pow(6) }> = RecordingList::new(LinkedListSkipList::new());
+        recording.insert("a");
+        recording.remove(&"b");
+        assert_eq!(recording.get(&"a"), Some(&"a"));
+        let mut log = Vec::new();
+        recording.write_log(&mut log).unwrap();
+        assert_eq!(String::from_utf8(log).unwrap(), "Insert(\"a\")\nRemove(\"b\")\nGet(\"a\")\n");
+        assert!(replay::<&str, { 2_usize.pow(6) }, _>(recording.ops().to_vec()).contains(&"a"));
+    }
+}
@@ -1,4 +1,53 @@
 
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Errors returned by skip list operations which could not be completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipListError {
+    /// The operation is not supported by the implementation. Holds a description of why.
+    Unsupported(&'static str),
+    /// No entry in the list satisfies the requested target.
+    OutOfRange,
+}
+
+impl Display for SkipListError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SkipListError::Unsupported(reason) => write!(f, "unsupported operation: {}", reason),
+            SkipListError::OutOfRange => write!(f, "no entry satisfies the target"),
+        }
+    }
+}
+
+impl Error for SkipListError {}
+
+/// Why a seek found no entry to position at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekError {
+    /// The list has no entries.
+    Empty,
+    /// The target is smaller than every key in the list.
+    BelowMinimum,
+    /// The target is larger than every key in the list.
+    AboveMaximum,
+}
+
+impl Display for SeekError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SeekError::Empty => write!(f, "the list is empty"),
+            SeekError::BelowMinimum => write!(f, "the target is below the smallest key"),
+            SeekError::AboveMaximum => write!(f, "the target is above the largest key"),
+        }
+    }
+}
+
+impl Error for SeekError {}
+
 pub trait SkipList<Key: Ord> {
 
     /// Inserts a key into the list.
@@ -8,8 +57,171 @@ pub trait SkipList<Key: Ord> {
     /// Returns true if an entry that compares equal to `key` is in the list.
     fn contains(&self, key: &Key) -> bool;
 
+    /// Removes the entry that compares equal to `key`. Returns true if one was in the list.
+    fn remove(&mut self, key: &Key) -> bool;
+
+    /// Returns the smallest entry in the list, or None if it is empty.
+    fn min(&self) -> Option<&Key>;
+
+    /// Returns the largest entry in the list, or None if it is empty.
+    fn max(&self) -> Option<&Key>;
+
     /// Returns the estimated number of entries smaller than `key`.
     fn estimate_count(&self, key: &Key) -> usize;
+
+    /// Returns the ratio of the expected cost of a search, from the number of entries on each
+    /// level, to the cost of a search in an ideally balanced list. Close to 1.0 when healthy,
+    /// growing as the list degenerates towards a linked list.
+    fn balance_factor(&self) -> f64;
+}
+
+/// Extracts the prefix of a key used for prefix scans and bloom filters. For a composite key such
+/// as `(user_id, timestamp)` the extractor can return just the `user_id` bytes so every entry for a
+/// user shares a prefix. Extracted prefixes must sort in the same order as the keys they come from.
+pub trait PrefixExtractor<Key>: Send + Sync {
+    fn prefix<'a>(&self, key: &'a Key) -> &'a [u8];
+}
+
+/// Uses the entire key as its prefix.
+pub struct IdentityExtractor;
+
+impl<Key: AsRef<[u8]>> PrefixExtractor<Key> for IdentityExtractor {
+    fn prefix<'a>(&self, key: &'a Key) -> &'a [u8] {
+        key.as_ref()
+    }
+}
+
+/// Orders keys by a transformed form, such as ignoring case, without storing the transformed
+/// form. Used through `TransformedKey`. Must be a total order.
+pub trait KeyTransform<Key>: Default {
+    fn transform_cmp(&self, a: &Key, b: &Key) -> Ordering;
+}
+
+/// Orders keys by their bytes with ASCII letters compared as lower case.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AsciiCaseInsensitive;
+
+impl<Key: AsRef<[u8]>> KeyTransform<Key> for AsciiCaseInsensitive {
+    fn transform_cmp(&self, a: &Key, b: &Key) -> Ordering {
+        let a = a.as_ref().iter().map(u8::to_ascii_lowercase);
+        let b = b.as_ref().iter().map(u8::to_ascii_lowercase);
+        a.cmp(b)
+    }
+}
+
+/// A key ordered, and compared for equality, by the transform `T` rather than by its own `Ord`.
+/// The key itself is kept as given, so iteration and flushes see it unchanged. Keys which `T`
+/// orders as equal are the same key in a list, writing one replaces the other.
+pub struct TransformedKey<Key, T> {
+    key: Key,
+    _transform: PhantomData<T>,
+}
+
+impl<Key, T: KeyTransform<Key>> TransformedKey<Key, T> {
+    pub fn new(key: Key) -> Self {
+        Self {
+            key,
+            _transform: PhantomData,
+        }
+    }
+
+    /// Returns the key as it was given.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    pub fn into_inner(self) -> Key {
+        self.key
+    }
+}
+
+impl<Key, T: KeyTransform<Key>> PartialEq for TransformedKey<Key, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<Key, T: KeyTransform<Key>> Eq for TransformedKey<Key, T> {}
+
+impl<Key, T: KeyTransform<Key>> PartialOrd for TransformedKey<Key, T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Key, T: KeyTransform<Key>> Ord for TransformedKey<Key, T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        T::default().transform_cmp(&self.key, &other.key)
+    }
+}
+
+impl<Key: Default, T: KeyTransform<Key>> Default for TransformedKey<Key, T> {
+    fn default() -> Self {
+        Self::new(Key::default())
+    }
+}
+
+impl<Key: Debug, T> Debug for TransformedKey<Key, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.key.fmt(f)
+    }
+}
+
+impl<Key: AsRef<[u8]>, T> AsRef<[u8]> for TransformedKey<Key, T> {
+    fn as_ref(&self) -> &[u8] {
+        self.key.as_ref()
+    }
+}
+
+/// Measures how far apart two keys are, for finding the nearest key. Must not overflow for any
+/// pair of keys, so integers measure in the unsigned type of the same width.
+pub trait Distance {
+    type Output: Ord;
+
+    fn distance(&self, other: &Self) -> Self::Output;
+}
+
+// Integers measure with `abs_diff`, which gives the unsigned type of the same width.
+macro_rules! distance_abs_diff {
+    ($($int:ty => $unsigned:ty),*) => {$(
+        impl Distance for $int {
+            type Output = $unsigned;
+
+            fn distance(&self, other: &Self) -> $unsigned {
+                self.abs_diff(*other)
+            }
+        }
+    )*};
+}
+
+distance_abs_diff!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128, isize => usize);
+distance_abs_diff!(u8 => u8, u16 => u16, u32 => u32, u64 => u64, u128 => u128, usize => usize);
+
+/// Receives the cost of searches through a list, for diagnosing slow lookups. Searches through a
+/// shared list may report from several threads at once.
+pub trait MetricsSink: Send + Sync {
+    /// Called once per search with the number of key comparisons it made and the number of times
+    /// it dropped down a level.
+    fn record_search(&self, comparisons: usize, level_descents: usize);
+
+    /// Called when a search made with read repair enabled finds a node missing from `level`,
+    /// although its tower reaches it, and links it back in.
+    fn record_repair(&self, _level: usize) {}
+}
+
+/// Source of the current time used to expire entries. Only needs to be monotonic, the unit is
+/// whatever the ttls passed to the list are measured in.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> u64;
+}
+
+/// Milliseconds since the unix epoch.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis() as u64)
+    }
 }
 
 pub trait SkipListIterator<Key>: Iterator<Item = Key> {
@@ -17,29 +229,84 @@ pub trait SkipListIterator<Key>: Iterator<Item = Key> {
     fn valid(&self) -> bool;
 
     /// Returns the key at the current position.
-    /// Requires that the iterator is valid.
+    /// Requires that the iterator is valid, which debug builds assert.
     fn key(&self) -> Option<Key>;
 
     /// Advances to the next position. Used in place of next which needs to be defined for the
-    /// super trait. Requires that the iterator is valid.
+    /// super trait. Requires that the iterator is valid, which debug builds assert. Advancing past
+    /// the last entry leaves the iterator invalid.
     fn advance(&mut self) ;
 
-    /// Advances to the previous position.
-    /// Requires that the iterator is valid.
-    fn prev(&mut self);
+    /// Advances `n` positions using the upper levels of the list to skip ahead. If the end of the
+    /// list is reached before `n` positions the iterator becomes invalid.
+    fn skip_forward(&mut self, n: usize);
+
+    /// Returns true if the iterator can move backwards through `prev`, `seek_for_prev` and
+    /// `seek_to_last`. When false those methods return `SkipListError::Unsupported`.
+    fn supports_reverse(&self) -> bool;
+
+    /// Advances to the previous position. Requires that the iterator is valid, which debug builds
+    /// assert. Moving back from the first entry leaves the iterator invalid.
+    fn prev(&mut self) -> Result<(), SkipListError>;
 
-    /// Advance to the first entry with a key >= target.
-    fn seek(&mut self, target: Key);
+    /// Advance to the first entry with a key >= target, returning true if its key is equal to
+    /// target. A target below the smallest key positions at the first entry. Returns
+    /// `SeekError::Empty` or `SeekError::AboveMaximum`, leaving the position unchanged, if there
+    /// is no such entry.
+    fn seek(&mut self, target: Key) -> Result<bool, SeekError>;
+
+    /// Position at the entry with a key equal to target, returning false and leaving the position
+    /// unchanged if there is none. Returns `SeekError::BelowMinimum` or `SeekError::AboveMaximum`
+    /// when target is outside the keys of the list, and `SeekError::Empty` when it has none.
+    fn seek_exact(&mut self, target: Key) -> Result<bool, SeekError>;
 
     /// Retreat to the last entry with a key <= target.
-    fn seek_for_prev(&mut self, target: Key);
+    fn seek_for_prev(&mut self, target: Key) -> Result<(), SkipListError>;
 
     /// Position at the first entry in list.
     /// Final state of iterator is Valid() iff list is not empty.
     fn seek_to_first(&mut self);
 
+    /// Position before the first entry so the next call to `next` or `advance` moves to the
+    /// smallest key, for replaying a scan from the start.
+    fn reset(&mut self);
+
     /// Position at the last entry in list.
     /// Final state of iterator is Valid() iff list is not empty.
-    fn seek_to_last(&mut self);
+    fn seek_to_last(&mut self) -> Result<(), SkipListError>;
     type Item;
+
+    /// Returns a plain `Iterator` over the keys after the current position, stepping with
+    /// `advance` and reading with `key`, for use with the std combinators where the methods of
+    /// this trait would be ambiguous. Like `next`, a fresh or `reset` iterator yields every key.
+    fn as_std_iter(&mut self) -> StdIter<'_, Key, Self>
+    where
+        Self: Sized,
+    {
+        StdIter {
+            inner: self,
+            _key: PhantomData,
+        }
+    }
+}
+
+/// Iterator over the keys of a `SkipListIterator`, created by `SkipListIterator::as_std_iter`.
+pub struct StdIter<'i, Key, I> {
+    inner: &'i mut I,
+    _key: PhantomData<fn() -> Key>,
+}
+
+impl<'i, Key, I: SkipListIterator<Key>> Iterator for StdIter<'i, Key, I> {
+    type Item = Key;
+
+    fn next(&mut self) -> Option<Key> {
+        if !self.inner.valid() {
+            return None;
+        }
+        self.inner.advance();
+        if !self.inner.valid() {
+            return None;
+        }
+        self.inner.key()
+    }
 }
@@ -0,0 +1,95 @@
+use std::ops::Bound;
+
+use crate::memtable::map::SkipMap;
+
+/// The operations higher layers need from an ordered key value store, so they can be written
+/// against any storage engine. Object safe, a store can be used as `dyn OrderedStore<K, V>`.
+pub trait OrderedStore<K: Ord, V> {
+    /// Stores `value` under `key`, returning the value previously stored under the key.
+    fn put(&mut self, key: K, value: V) -> Option<V>;
+
+    /// Returns the value stored under `key`.
+    fn get(&self, key: &K) -> Option<&V>;
+
+    /// Removes the value stored under `key`, returning it.
+    fn delete(&mut self, key: &K) -> Option<V>;
+
+    /// Returns the entries whose keys fall between the bounds, in ascending key order.
+    fn range(&self, range: (Bound<K>, Bound<K>)) -> Box<dyn Iterator<Item = (&K, &V)> + '_>;
+
+    /// Returns the number of entries in the store.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K: Ord, V, const MAX_HEIGHT: usize> OrderedStore<K, V> for SkipMap<K, V, MAX_HEIGHT> {
+    fn put(&mut self, key: K, value: V) -> Option<V> {
+        self.insert(key, value)
+    }
+
+    fn get(&self, key: &K) -> Option<&V> {
+        SkipMap::get(self, key)
+    }
+
+    fn delete(&mut self, key: &K) -> Option<V> {
+        self.remove(key)
+    }
+
+    fn range(&self, range: (Bound<K>, Bound<K>)) -> Box<dyn Iterator<Item = (&K, &V)> + '_> {
+        Box::new(SkipMap::range(self, range))
+    }
+
+    fn len(&self) -> usize {
+        SkipMap::len(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Exercises a store only through the trait, as a caller generic over the engine would.
+    fn exercise(store: &mut dyn OrderedStore<u32, String>) {
+        assert!(store.is_empty());
+        for key in (0..100).rev() {
+            assert_eq!(store.put(key, format!("v{}", key)), None);
+        }
+        assert_eq!(store.len(), 100);
+        assert_eq!(store.put(7, "seven".to_string()), Some("v7".to_string()));
+        assert_eq!(store.get(&7).map(String::as_str), Some("seven"));
+        assert_eq!(store.get(&100), None);
+
+        assert_eq!(store.delete(&7), Some("seven".to_string()));
+        assert_eq!(store.delete(&7), None);
+        assert_eq!(store.get(&7), None);
+        assert_eq!(store.len(), 99);
+
+        let keys: Vec<u32> = store.range((Bound::Included(5), Bound::Excluded(10))).map(|(key, _)| *key).collect();
+        assert_eq!(keys, vec![5, 6, 8, 9]);
+        let keys: Vec<u32> = store.range((Bound::Excluded(95), Bound::Unbounded)).map(|(key, _)| *key).collect();
+        assert_eq!(keys, vec![96, 97, 98, 99]);
+        assert_eq!(store.range((Bound::Unbounded, Bound::Unbounded)).count(), 99);
+        assert!(store.range((Bound::Included(200), Bound::Unbounded)).next().is_none());
+        let (key, value) = store.range((Bound::Unbounded, Bound::Included(0))).next().unwrap();
+        assert_eq!((*key, value.as_str()), (0, "v0"));
+
+        for key in 0..100 {
+            store.delete(&key);
+        }
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_ordered_store_through_trait_object() {
+        let mut stores: Vec<Box<dyn OrderedStore<u32, String>>> = vec![
+            Box::new(SkipMap::<u32, String>::new()),
+            Box::new(SkipMap::<u32, String, { 2_usize.pow(4) }>::new()),
+        ];
+        for store in &mut stores {
+            exercise(store.as_mut());
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::memtable::linkedlist_skiplist::{Iter, LinkedListSkipList};
+use crate::memtable::merge::MergingIterator;
+use crate::memtable::skiplist::SkipList;
+
+/// Splits keys across `N_STRIPES` lists, each behind its own lock, by the hash of the key. Writes
+/// to keys in different stripes run in parallel. Ordered scans lock every stripe and merge them.
+pub struct StripedSkipList<Key: Ord + Default, const N_STRIPES: usize, const MAX_HEIGHT: usize = 32> {
+    stripes: [Mutex<LinkedListSkipList<'static, Key, MAX_HEIGHT>>; N_STRIPES],
+}
+
+impl<Key: Ord + Default + Hash, const N_STRIPES: usize, const MAX_HEIGHT: usize> StripedSkipList<Key, N_STRIPES, MAX_HEIGHT> {
+    pub fn new() -> Self {
+        assert!(N_STRIPES > 0, "a striped list needs at least one stripe");
+        Self {
+            stripes: std::array::from_fn(|_| Mutex::new(LinkedListSkipList::new())),
+        }
+    }
+
+    /// Inserts `key`, returning the equal key it replaced.
+    pub fn insert(&self, key: Key) -> Option<Key> {
+        self.stripe(&key).replace(key)
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.stripe(key).contains(key)
+    }
+
+    /// Returns a clone of the key equal to `key`. A reference cannot outlive the stripe's lock.
+    pub fn get(&self, key: &Key) -> Option<Key>
+    where
+        Key: Clone,
+    {
+        self.stripe(key).get(key).cloned()
+    }
+
+    pub fn remove(&self, key: &Key) -> bool {
+        self.stripe(key).remove(key)
+    }
+
+    /// Returns the number of keys across every stripe. Writes made while the stripes are counted
+    /// may or may not be included.
+    pub fn len(&self) -> usize {
+        self.stripes.iter().map(|stripe| stripe.lock().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Locks every stripe, in order, for an ordered scan through `StripesGuard::iter`. Writes
+    /// wait until the guard is dropped.
+    pub fn lock_all(&self) -> StripesGuard<'_, Key, MAX_HEIGHT> {
+        StripesGuard {
+            stripes: self.stripes.iter().map(|stripe| stripe.lock().unwrap()).collect(),
+        }
+    }
+
+    fn stripe(&self, key: &Key) -> MutexGuard<'_, LinkedListSkipList<'static, Key, MAX_HEIGHT>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        self.stripes[(hasher.finish() % N_STRIPES as u64) as usize].lock().unwrap()
+    }
+}
+
+impl<Key: Ord + Default + Hash, const N_STRIPES: usize, const MAX_HEIGHT: usize> Default for StripedSkipList<Key, N_STRIPES, MAX_HEIGHT> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Every stripe of a `StripedSkipList` locked, created by `StripedSkipList::lock_all`.
+pub struct StripesGuard<'s, Key: Ord + Default, const MAX_HEIGHT: usize> {
+    stripes: Vec<MutexGuard<'s, LinkedListSkipList<'static, Key, MAX_HEIGHT>>>,
+}
+
+impl<'s, Key: Ord + Default, const MAX_HEIGHT: usize> StripesGuard<'s, Key, MAX_HEIGHT> {
+    /// Returns an iterator over the keys of every stripe in ascending order.
+    pub fn iter(&self) -> MergingIterator<Iter<'_, Key>> {
+        MergingIterator::new(self.stripes.iter().map(|stripe| stripe.iter()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concurrent_striped_writers() {
+        let list: StripedSkipList<u32, 8, { 2_usize.pow(6) }> = StripedSkipList::new();
+        let writers = 8;
+        let per_writer = 2_000;
+        std::thread::scope(|scope| {
+            for writer in 0..writers {
+                let list = &list;
+                scope.spawn(move || {
+                    // Interleave the writers' keys so every stripe sees every writer.
+                    for _i in 0..per_writer {
+                        assert_eq!(list.insert(_i * writers + writer), None);
+                    }
+                });
+            }
+        });
+        assert_eq!(list.len(), (writers * per_writer) as usize);
+        for key in 0..writers * per_writer {
+            assert!(list.contains(&key));
+            assert_eq!(list.get(&key), Some(key));
+        }
+        assert!(!list.contains(&(writers * per_writer)));
+        let guard = list.lock_all();
+        assert!(guard.stripes.iter().all(|stripe| !stripe.is_empty()));
+        assert!(guard.iter().copied().eq(0..writers * per_writer));
+        drop(guard);
+
+        assert_eq!(list.insert(5), Some(5));
+        assert!(list.remove(&5));
+        assert!(!list.contains(&5));
+        assert_eq!(list.lock_all().iter().count(), (writers * per_writer) as usize - 1);
+    }
+}